@@ -0,0 +1,77 @@
+use crate::crawl::CRAWL_DIR;
+use crate::matrix::MATRIX_DIR;
+use crate::observer::TARGETS_DIR;
+use crate::regression::{BASELINE_DIR, TEST_DIR};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// the files written by observer in the capture directory start with "observed",
+// its directories (matrix, crawl, additional targets, test runs and baselines) are listed
+// recursively
+pub fn archive_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("observed"))
+        {
+            files.push(path);
+        }
+    }
+    for sub_dir in [MATRIX_DIR, CRAWL_DIR, TARGETS_DIR, TEST_DIR, BASELINE_DIR] {
+        let sub_dir = dir.join(sub_dir);
+        if sub_dir.is_dir() {
+            collect_files(&sub_dir, &mut files)?;
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else {
+        files.push(path.to_owned());
+    }
+
+    Ok(())
+}
+
+pub fn list_captures(dir: &Path, file_format: &str) -> anyhow::Result<Vec<(PathBuf, SystemTime)>> {
+    let mut captures = Vec::new();
+    for file in archive_files(dir)? {
+        if file.extension().is_some_and(|e| e == file_format) {
+            let modified = fs::metadata(&file)?.modified()?;
+            captures.push((file, modified));
+        }
+    }
+
+    Ok(captures)
+}
+
+// copies the archive into out, keeping paths relative to the capture directory,
+// without the files of a previous export into out
+pub fn export_archive(dir: &Path, out: &Path) -> anyhow::Result<usize> {
+    fs::create_dir_all(out)?;
+    let out_dir = out.canonicalize()?;
+    let files: Vec<PathBuf> = archive_files(dir)?
+        .into_iter()
+        .filter(|f| !f.canonicalize().is_ok_and(|f| f.starts_with(&out_dir)))
+        .collect();
+    for file in &files {
+        let destination = out.join(file.strip_prefix(dir)?);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(file, destination)?;
+    }
+
+    Ok(files.len())
+}
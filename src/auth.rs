@@ -0,0 +1,276 @@
+use anyhow::anyhow;
+use headless_chrome::protocol::cdp::Network::{self, CookieParam};
+use headless_chrome::Tab;
+use std::collections::BTreeMap;
+use std::{env, fs};
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Auth {
+    #[schemars(description = "Cookies set before navigating")]
+    pub cookies: Vec<Cookie>,
+    #[schemars(description = "Netscape cookies.txt file of cookies set before navigating")]
+    pub cookie_file: Option<String>,
+    #[schemars(
+        description = "Extra HTTP headers, values can reference environment variables with ${VAR}"
+    )]
+    pub headers: BTreeMap<String, String>,
+    #[schemars(description = "HTTP basic authentication")]
+    pub basic_auth: Option<BasicAuth>,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+pub struct Cookie {
+    #[schemars(description = "Cookie name")]
+    pub name: String,
+    #[schemars(description = "Cookie value, can reference an environment variable with ${VAR}")]
+    pub value: String,
+    #[schemars(description = "Cookie domain")]
+    pub domain: Option<String>,
+    #[schemars(description = "Cookie path")]
+    pub path: Option<String>,
+    #[schemars(description = "Secure cookie")]
+    pub secure: Option<bool>,
+    #[schemars(description = "HTTP only cookie")]
+    pub http_only: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+pub struct BasicAuth {
+    #[schemars(
+        description = "Basic auth username, can reference an environment variable with ${VAR}"
+    )]
+    pub username: String,
+    #[schemars(
+        description = "Basic auth password, can reference an environment variable with ${VAR}"
+    )]
+    pub password: String,
+}
+
+impl Auth {
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+            && self.cookie_file.is_none()
+            && self.headers.is_empty()
+            && self.basic_auth.is_none()
+    }
+}
+
+pub fn parse_auth(a: &Auth) -> Result<(), (String, String)> {
+    for (i, c) in a.cookies.iter().enumerate() {
+        expand_env(&c.value).map_err(|e| (format!("cookies[{}].value", i), e))?;
+    }
+
+    for (name, v) in &a.headers {
+        expand_env(v).map_err(|e| (format!("headers.{}", name), e))?;
+    }
+
+    if let Some(b) = &a.basic_auth {
+        expand_env(&b.username).map_err(|e| ("basic_auth.username".to_owned(), e))?;
+        expand_env(&b.password).map_err(|e| ("basic_auth.password".to_owned(), e))?;
+    }
+
+    if let Some(path) = &a.cookie_file {
+        read_cookie_file(path).map_err(|e| ("cookie_file".to_owned(), e))?;
+    }
+
+    Ok(())
+}
+
+// secrets are written as ${VAR} and read from the environment so that
+// they do not have to be stored in config.yaml
+pub fn expand_env(value: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated variable in '{}'", value))?;
+        let name = &rest[start + 2..start + end];
+        if name.is_empty() {
+            return Err(format!("empty variable name in '{}'", value));
+        }
+        let var =
+            env::var(name).map_err(|_| format!("environment variable '{}' is not set", name))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+// Netscape cookie jar format as written by curl and most browser extensions:
+// domain, include subdomains, path, secure, expires, name, value (tab separated)
+fn read_cookie_file(path: &str) -> Result<Vec<CookieParam>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("cookie file '{}': {}", path, e))?;
+    let mut cookies = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(l) => (l, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() != 7 {
+            return Err(format!(
+                "cookie file '{}' line {} must have 7 tab separated fields",
+                path,
+                i + 1
+            ));
+        }
+
+        let mut cookie = cookie_param(fields[5], fields[6].to_owned());
+        cookie.domain = Some(fields[0].to_owned());
+        cookie.path = Some(fields[2].to_owned());
+        cookie.secure = Some(fields[3].eq_ignore_ascii_case("true"));
+        cookie.http_only = Some(http_only);
+        // session cookies have an expiry of 0
+        cookie.expires = fields[4].parse().ok().filter(|e| *e > 0.0);
+        cookies.push(cookie);
+    }
+
+    Ok(cookies)
+}
+
+fn cookie_param(name: &str, value: String) -> CookieParam {
+    CookieParam {
+        name: name.to_owned(),
+        value,
+        url: None,
+        domain: None,
+        path: None,
+        secure: None,
+        http_only: None,
+        same_site: None,
+        expires: None,
+        priority: None,
+        same_party: None,
+        source_scheme: None,
+        source_port: None,
+        partition_key: None,
+    }
+}
+
+pub fn apply_auth(tab: &Tab, auth: &Auth, url: &str) -> anyhow::Result<()> {
+    let mut cookies = match &auth.cookie_file {
+        Some(path) => read_cookie_file(path).map_err(|e| anyhow!("Error: {}", e))?,
+        None => Vec::new(),
+    };
+    for c in &auth.cookies {
+        let value = expand_env(&c.value).map_err(|e| anyhow!("Error: {}", e))?;
+        let mut cookie = cookie_param(&c.name, value);
+        // cookies without a domain are scoped to the observed URL
+        cookie.url = c.domain.is_none().then(|| url.to_owned());
+        cookie.domain = c.domain.clone();
+        cookie.path = c.path.clone();
+        cookie.secure = c.secure;
+        cookie.http_only = c.http_only;
+        cookies.push(cookie);
+    }
+
+    if !cookies.is_empty() {
+        tab.call_method(Network::SetCookies { cookies })?;
+    }
+
+    if !auth.headers.is_empty() {
+        let mut headers = BTreeMap::new();
+        for (name, value) in &auth.headers {
+            headers.insert(
+                name.as_str(),
+                expand_env(value).map_err(|e| anyhow!("Error: {}", e))?,
+            );
+        }
+        tab.set_extra_http_headers(headers.iter().map(|(n, v)| (*n, v.as_str())).collect())?;
+    }
+
+    if let Some(b) = &auth.basic_auth {
+        let username = expand_env(&b.username).map_err(|e| anyhow!("Error: {}", e))?;
+        let password = expand_env(&b.password).map_err(|e| anyhow!("Error: {}", e))?;
+        tab.enable_fetch(None, Some(true))?;
+        tab.authenticate(Some(username), Some(password))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_variables() {
+        env::set_var("OBSERVER_TEST_SECRET", "s3cr3t");
+        assert_eq!(expand_env("plain text").unwrap(), "plain text");
+        assert_eq!(
+            expand_env("Bearer ${OBSERVER_TEST_SECRET}!").unwrap(),
+            "Bearer s3cr3t!"
+        );
+        assert_eq!(
+            expand_env("${OBSERVER_TEST_SECRET}${OBSERVER_TEST_SECRET}").unwrap(),
+            "s3cr3ts3cr3t"
+        );
+        assert!(expand_env("${OBSERVER_TEST_MISSING}")
+            .unwrap_err()
+            .contains("'OBSERVER_TEST_MISSING' is not set"));
+        assert!(expand_env("a ${} b")
+            .unwrap_err()
+            .contains("empty variable name"));
+        assert!(expand_env("${OBSERVER_TEST_SECRET")
+            .unwrap_err()
+            .contains("unterminated"));
+        // a $ without braces is literal text
+        assert_eq!(expand_env("$5 {x}").unwrap(), "$5 {x}");
+    }
+
+    fn cookie_file(name: &str, contents: &str) -> Result<Vec<CookieParam>, String> {
+        let path = env::temp_dir().join(format!("observer_{}_{}", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let cookies = read_cookie_file(&path.to_string_lossy());
+        fs::remove_file(path).unwrap();
+        cookies
+    }
+
+    #[test]
+    fn netscape_cookie_file() {
+        let cookies = cookie_file(
+            "cookies",
+            "# Netscape HTTP Cookie File\n\
+            \n\
+            .gnu.org\tTRUE\t/\tTRUE\t1999999999\tsession\tabc\n\
+            #HttpOnly_www.gnu.org\tFALSE\t/software\tFALSE\t0\ttoken\txyz\n",
+        )
+        .unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].name, "session");
+        assert_eq!(cookies[0].value, "abc");
+        assert_eq!(cookies[0].domain.as_deref(), Some(".gnu.org"));
+        assert_eq!(cookies[0].secure, Some(true));
+        assert_eq!(cookies[0].http_only, Some(false));
+        assert_eq!(cookies[0].expires, Some(1999999999.0));
+        assert_eq!(cookies[1].domain.as_deref(), Some("www.gnu.org"));
+        assert_eq!(cookies[1].path.as_deref(), Some("/software"));
+        assert_eq!(cookies[1].http_only, Some(true));
+        assert_eq!(cookies[1].expires, None);
+    }
+
+    #[test]
+    fn malformed_cookie_file() {
+        let error = cookie_file(
+            "malformed",
+            "# comment\n.gnu.org TRUE / TRUE 0 session abc\n",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("line 2 must have 7 tab separated fields"),
+            "{}",
+            error
+        );
+        assert!(read_cookie_file("/nonexistent/observer_cookies.txt").is_err());
+    }
+}
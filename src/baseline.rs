@@ -0,0 +1,227 @@
+use crate::auth::expand_env;
+use crate::regression::{baseline_filename, TestCase, BASELINE_DIR, TEST_REPORT_FILE};
+use crate::settings::FILE_FORMATS;
+use anyhow::bail;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const BASELINE_HISTORY_FILE: &str = "observer_baselines/history.jsonl";
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct BaselineEntry {
+    pub name: String,
+    pub version: String,
+    pub action: String,
+    pub by: String,
+    pub at: String,
+    pub file: String,
+}
+
+// web approvals need the token, read from the environment like other secrets
+pub fn parse_approve_token(t: &str) -> Result<String, String> {
+    let token = expand_env(t)?;
+    if token.chars().count() < 8 {
+        return Err("approve token must be at least 8 characters long".to_owned());
+    }
+
+    Ok(token)
+}
+
+fn history_filename(name: &str, version: &str, file_format: &str) -> String {
+    format!(
+        "{}/history/{}/{}.{}",
+        BASELINE_DIR, name, version, file_format
+    )
+}
+
+pub fn read_test_report(dir: &Path) -> Vec<TestCase> {
+    fs::read_to_string(dir.join(TEST_REPORT_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn read_history(dir: &Path, name: &str) -> Vec<BaselineEntry> {
+    fs::read_to_string(dir.join(BASELINE_HISTORY_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str::<BaselineEntry>(l).ok())
+        .filter(|e| e.name == name)
+        .collect()
+}
+
+// who approved a baseline when not given explicitly
+pub fn default_approver() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_owned())
+}
+
+// promotes the captures of the last test run matching name (a target,
+// including its matrix combinations, or a single test case) to baselines,
+// the files of the test report and history are relative to dir
+pub fn approve(dir: &Path, name: &str, by: &str) -> anyhow::Result<Vec<BaselineEntry>> {
+    let cases: Vec<TestCase> = read_test_report(dir)
+        .into_iter()
+        .filter(|c| c.target == name || c.name == name)
+        .collect();
+    if cases.is_empty() {
+        bail!(
+            "Error: No capture of '{}' found in {}\nRun observer test first",
+            name,
+            dir.join(TEST_REPORT_FILE).display()
+        );
+    }
+
+    let mut entries = Vec::new();
+    for case in cases {
+        if !dir.join(&case.current).exists() {
+            bail!(
+                "Error: Capture {} of '{}' not found",
+                case.current,
+                case.name
+            );
+        }
+
+        let version = chrono::offset::Local::now()
+            .format("%Y%m%d%H%M%S%3f")
+            .to_string();
+        let file = history_filename(&case.name, &version, extension(&case.current));
+        if let Some(history_dir) = dir.join(&file).parent() {
+            fs::create_dir_all(history_dir)?;
+        }
+        fs::copy(dir.join(&case.current), dir.join(&file))?;
+        entries.push(promote(dir, &case.name, &version, "approve", by, file)?);
+    }
+
+    Ok(entries)
+}
+
+// restores version, or the baseline approved before the current one
+pub fn rollback(
+    dir: &Path,
+    name: &str,
+    version: Option<&str>,
+    by: &str,
+) -> anyhow::Result<BaselineEntry> {
+    let history = read_history(dir, name);
+    let approvals: Vec<&BaselineEntry> = history.iter().filter(|e| e.action == "approve").collect();
+    let entry = match version {
+        Some(v) => approvals.iter().find(|e| e.version == v),
+        None => history.last().and_then(|current| {
+            let i = approvals
+                .iter()
+                .position(|e| e.version == current.version)?;
+            approvals.get(i.checked_sub(1)?)
+        }),
+    };
+    let entry = match entry {
+        Some(e) => (*e).clone(),
+        None => match version {
+            Some(v) => bail!("Error: Baseline version '{}' of '{}' not found", v, name),
+            None => bail!("Error: No previous baseline of '{}' to roll back to", name),
+        },
+    };
+
+    promote(dir, name, &entry.version, "rollback", by, entry.file)
+}
+
+fn promote(
+    dir: &Path,
+    name: &str,
+    version: &str,
+    action: &str,
+    by: &str,
+    file: String,
+) -> anyhow::Result<BaselineEntry> {
+    // a baseline in another file format would be preferred by test runs in that format
+    for file_format in FILE_FORMATS.iter().filter(|f| **f != extension(&file)) {
+        match fs::remove_file(dir.join(baseline_filename(name, file_format))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => (),
+        }
+    }
+    fs::copy(
+        dir.join(&file),
+        dir.join(baseline_filename(name, extension(&file))),
+    )?;
+    let entry = BaselineEntry {
+        name: name.to_owned(),
+        version: version.to_owned(),
+        action: action.to_owned(),
+        by: by.to_owned(),
+        at: chrono::offset::Local::now().to_rfc3339(),
+        file,
+    };
+    let mut history = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(BASELINE_HISTORY_FILE))?;
+    writeln!(history, "{}", serde_json::to_string(&entry)?)?;
+
+    Ok(entry)
+}
+
+// baselines keep the file format of the capture they were approved from
+fn extension(filename: &str) -> &str {
+    filename.rsplit('.').next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regression::TEST_DIR;
+
+    fn capture(dir: &Path, contents: &str) {
+        fs::write(dir.join(TEST_DIR).join("home.png"), contents).unwrap();
+        // versions are timestamps with millisecond precision
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    fn baseline(dir: &Path) -> String {
+        fs::read_to_string(dir.join(baseline_filename("home", "png"))).unwrap()
+    }
+
+    #[test]
+    fn approve_and_rollback() {
+        let dir = std::env::temp_dir().join(format!("observer_baseline_{}", std::process::id()));
+        fs::create_dir_all(dir.join(TEST_DIR)).unwrap();
+        let report = serde_json::json!([{
+            "name": "home", "target": "home", "url": "https://www.gnu.org", "browser": "chrome",
+            "viewport": "1920x1080", "status": "missing_baseline", "diff_percent": null,
+            "baseline": "observer_baselines/home.png", "current": "observed_test/home.png",
+            "diff": null, "message": null, "duration_ms": 1.0,
+        }]);
+        fs::write(dir.join(TEST_REPORT_FILE), report.to_string()).unwrap();
+
+        capture(&dir, "v1");
+        let v1 = approve(&dir, "home", "alice").unwrap().remove(0);
+        assert!(rollback(&dir, "home", None, "bob").is_err());
+        capture(&dir, "v2");
+        let v2 = approve(&dir, "home", "alice").unwrap().remove(0);
+        assert_eq!(baseline(&dir), "v2");
+
+        // the baseline approved before the current one
+        let entry = rollback(&dir, "home", None, "bob").unwrap();
+        assert_eq!(entry.version, v1.version);
+        assert_eq!(baseline(&dir), "v1");
+        let history = read_history(&dir, "home");
+        let last = history.last().unwrap();
+        assert_eq!(
+            (&*last.action, &*last.by, &last.version, &last.file),
+            ("rollback", "bob", &v1.version, &v1.file)
+        );
+        assert_eq!(history.len(), 3);
+        assert!(rollback(&dir, "home", None, "bob").is_err());
+
+        let entry = rollback(&dir, "home", Some(&v2.version), "carol").unwrap();
+        assert_eq!(entry.version, v2.version);
+        assert_eq!(baseline(&dir), "v2");
+        assert_eq!(read_history(&dir, "home").len(), 4);
+        assert!(rollback(&dir, "home", Some("19700101000000000"), "carol").is_err());
+        assert!(read_history(&dir, "other").is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
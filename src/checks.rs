@@ -0,0 +1,224 @@
+use crate::metadata::{CaptureMetadata, Redirect};
+use chrono::TimeZone;
+use headless_chrome::protocol::cdp::types::Event;
+use headless_chrome::protocol::cdp::Network::{self, ResourceType};
+use headless_chrome::Tab;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Checks {
+    #[schemars(
+        description = "Status codes or ranges failing the capture (ex: '404' or '500-599')"
+    )]
+    pub fail_on_status: Vec<String>,
+    #[schemars(description = "Warn when the TLS certificate expires within this many days")]
+    pub tls_expiry_warning_days: u16,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self {
+            fail_on_status: vec!["400-599".to_owned()],
+            tls_expiry_warning_days: 14,
+        }
+    }
+}
+
+pub fn parse_checks(c: &Checks) -> Result<(), (String, String)> {
+    for (i, range) in c.fail_on_status.iter().enumerate() {
+        if parse_status_range(range).is_none() {
+            return Err((
+                format!("fail_on_status[{}]", i),
+                format!(
+                    "status range '{}' must follow this format: '404' or '500-599'",
+                    range
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_status_range(r: &str) -> Option<(u32, u32)> {
+    let (start, end) = match r.trim().split_once('-') {
+        Some((s, e)) => (s.trim().parse().ok()?, e.trim().parse().ok()?),
+        None => {
+            let status = r.trim().parse().ok()?;
+            (status, status)
+        }
+    };
+
+    if !(100..=599).contains(&start) || !(100..=599).contains(&end) || start > end {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+#[derive(Default)]
+struct DocumentResponse {
+    status: Option<u32>,
+    redirects: Vec<Redirect>,
+    ttfb_ms: Option<f64>,
+    tls_valid_to: Option<f64>,
+}
+
+// records the main document response of a tab from CDP Network events
+pub struct DocumentRecorder(Arc<Mutex<DocumentResponse>>);
+impl DocumentRecorder {
+    pub fn attach(tab: &Tab) -> anyhow::Result<Self> {
+        tab.call_method(Network::Enable {
+            max_total_buffer_size: None,
+            max_resource_buffer_size: None,
+            max_post_data_size: None,
+        })?;
+
+        let response = Arc::new(Mutex::new(DocumentResponse::default()));
+        let main_frame = tab.get_target_id().clone();
+        let recorder = response.clone();
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            let mut r = recorder.lock().expect("Locking document response");
+            match event {
+                Event::NetworkRequestWillBeSent(e)
+                    if e.params.Type == Some(ResourceType::Document)
+                        && e.params.frame_id.as_ref() == Some(&main_frame) =>
+                {
+                    match &e.params.redirect_response {
+                        Some(redirect) => r.redirects.push(Redirect {
+                            url: redirect.url.clone(),
+                            status: redirect.status,
+                        }),
+                        // a new navigation starts from scratch
+                        None => *r = DocumentResponse::default(),
+                    }
+                }
+                Event::NetworkResponseReceived(e)
+                    if e.params.Type == ResourceType::Document
+                        && e.params.frame_id.as_ref() == Some(&main_frame) =>
+                {
+                    let response = &e.params.response;
+                    r.status = Some(response.status);
+                    r.ttfb_ms = response.timing.as_ref().map(|t| t.receive_headers_end);
+                    r.tls_valid_to = response.security_details.as_ref().map(|s| s.valid_to);
+                }
+                _ => (),
+            }
+        }))?;
+
+        Ok(Self(response))
+    }
+
+    pub fn fill(&self, metadata: &mut CaptureMetadata) {
+        let r = self.0.lock().expect("Locking document response");
+        metadata.status = r.status;
+        metadata.redirects = r.redirects.clone();
+        metadata.ttfb_ms = r.ttfb_ms;
+        if let Some(valid_to) = r.tls_valid_to {
+            if let Some(expiry) = chrono::Utc.timestamp_opt(valid_to as i64, 0).single() {
+                metadata.tls_expiry = Some(expiry.to_rfc3339());
+            }
+        }
+    }
+}
+
+// a failed check is recorded in the metadata, observe keeps its schedule while
+// capture and test turn it into an error
+pub fn run_checks(checks: &Checks, metadata: &mut CaptureMetadata) -> anyhow::Result<()> {
+    if let Some(expiry) = &metadata.tls_expiry {
+        let expiry = chrono::DateTime::parse_from_rfc3339(expiry)?;
+        let days_left = (expiry.timestamp() - chrono::Utc::now().timestamp()) / 86400;
+        if days_left <= checks.tls_expiry_warning_days as i64 {
+            metadata.warnings.push(format!(
+                "TLS certificate of {} expires in {} day(s)",
+                metadata.url, days_left
+            ));
+        }
+    }
+
+    if let Some(status) = metadata.status {
+        for range in &checks.fail_on_status {
+            if let Some((start, end)) = parse_status_range(range) {
+                if (start..=end).contains(&status) {
+                    metadata.error = Some(format!(
+                        "Capture of {} failed\nHTTP status {} is in range {}",
+                        metadata.url, status, range
+                    ));
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_ranges() {
+        for (range, expected) in [
+            ("404", Some((404, 404))),
+            (" 500-599 ", Some((500, 599))),
+            ("500 - 599", Some((500, 599))),
+            ("599-500", None),
+            ("99", None),
+            ("600", None),
+            ("400-600", None),
+            ("4xx", None),
+            ("-404", None),
+            ("404-", None),
+            ("", None),
+        ] {
+            assert_eq!(parse_status_range(range), expected, "{:?}", range);
+        }
+        assert!(parse_checks(&Checks::default()).is_ok());
+        let checks = Checks {
+            fail_on_status: vec!["404".to_owned(), "5xx".to_owned()],
+            ..Default::default()
+        };
+        let (path, error) = parse_checks(&checks).unwrap_err();
+        assert_eq!(path, "fail_on_status[1]");
+        assert!(error.contains("'5xx'"));
+    }
+
+    fn checked(status: Option<u32>, expires_in_days: Option<i64>) -> CaptureMetadata {
+        let checks = Checks {
+            fail_on_status: vec!["404".to_owned(), "500-599".to_owned()],
+            tls_expiry_warning_days: 14,
+        };
+        let mut metadata = CaptureMetadata::new("https://www.gnu.org");
+        metadata.status = status;
+        metadata.tls_expiry =
+            expires_in_days.map(|d| (chrono::Utc::now() + chrono::Duration::days(d)).to_rfc3339());
+        run_checks(&checks, &mut metadata).unwrap();
+        metadata
+    }
+
+    #[test]
+    fn failing_status() {
+        assert!(checked(Some(200), None).error.is_none());
+        assert!(checked(Some(403), None).error.is_none());
+        assert!(checked(Some(404), None).error.is_some());
+        let error = checked(Some(503), None).error.unwrap();
+        assert!(
+            error.contains("HTTP status 503 is in range 500-599"),
+            "{}",
+            error
+        );
+        assert!(checked(None, None).error.is_none());
+    }
+
+    #[test]
+    fn tls_expiry() {
+        assert!(checked(Some(200), Some(90)).warnings.is_empty());
+        let warnings = checked(Some(200), Some(10)).warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("expires in"), "{}", warnings[0]);
+        // a warning does not fail the capture
+        assert!(checked(Some(200), Some(-1)).error.is_none());
+    }
+}
@@ -1,11 +1,49 @@
+use crate::proxy::parse_proxy_server;
 use crate::settings::{
-    parse_file_format, parse_host, parse_server_ip, parse_target_browser, parse_url,
+    parse_file_format, parse_host, parse_interval, parse_server_ip, parse_target_browser, parse_url,
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[clap(name = "observer", author = "author: c928")]
 pub struct Args {
+    #[clap(subcommand)]
+    pub command: Command,
+
+    #[clap(
+        global = true,
+        short,
+        long,
+        help_heading = "Flags",
+        help = "If the quiet flag is enabled, observer\nwill not print logs to terminal in CLI\nmode"
+    )]
+    pub quiet: bool,
+
+    #[clap(
+        global = true,
+        short,
+        long,
+        help_heading = "Flags",
+        help = "Use a configuration file to read application\nsettings, searched in order: \
+        $OBSERVER_CONFIG,\n./config[.yaml,.yml,.toml,.json],\n~/.config/observer/config.yaml,\n\
+        /etc/observer/config.yaml\n"
+    )]
+    pub config_file: bool,
+
+    #[clap(
+        global = true,
+        long,
+        takes_value = true,
+        required = false,
+        value_name = "PATH",
+        help = "Configuration file to read application\nsettings from (YAML, TOML or JSON,\n\
+        detected by extension)\n"
+    )]
+    pub config: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct CaptureArgs {
     #[clap(
         short,
         long,
@@ -17,14 +55,16 @@ pub struct Args {
     pub url: Option<String>,
 
     #[clap(
-        short,
+        short = 'b',
         long,
         takes_value = true,
         required = false,
-        default_value = "5",
-        help = "Interval between each browser capture\n"
+        default_value = "chrome",
+        help = "Target browser used to capture the website.\nAvailable targets are: chromium, \
+        chrome, edge\n",
+        value_parser = parse_target_browser,
     )]
-    pub interval: u16,
+    pub target_browser: String,
 
     #[clap(
         short,
@@ -38,24 +78,26 @@ pub struct Args {
     pub file_format: String,
 
     #[clap(
-        short,
         long,
-        help_heading = "Flags",
-        help = "If the quiet flag is enabled, observer\nwill not print logs to terminal in CLI\nmode"
+        takes_value = true,
+        required = false,
+        help = "Proxy server used by the target browser, also for the targets\nwithout a proxy of their own (ex: socks5://127.0.0.1:1080)\n",
+        value_parser = parse_proxy_server,
     )]
-    pub quiet: bool,
-
-    #[clap(short, long, help_heading = "Flags", help = "Launch observer's GUI")]
-    pub gui_mode: bool,
+    pub proxy: Option<String>,
+}
 
+#[derive(clap::Args)]
+pub struct ServerArgs {
     #[clap(
         short = 'H',
         long,
         takes_value = true,
         required = false,
         default_value = "0.0.0.0",
-        help = "IP address of the web server.\nHost can either be 127.0.0.1 to serve\nlocally on \
-        the host computer or 0.0.0.0\nto serve on the computer network\n",
+        help = "Comma separated addresses of the web\nserver: 127.0.0.1 to serve locally on\nthe host \
+        computer, 0.0.0.0 or :: to serve\non the computer network, a specific\ninterface address, \
+        [::1]:8080 to set\nthe port of an address or unix:<path>\nfor a Unix domain socket\n",
         value_parser = parse_host
     )]
     pub host: String,
@@ -76,28 +118,296 @@ pub struct Args {
         long,
         takes_value = true,
         required = false,
-        help = "Private IP of the hosting server\n(ex: 192.168.13.37)",
+        help = "IP printed in the server address\n(ex: 192.168.13.37), detected from the\nnetwork interfaces by default\n",
         value_parser = parse_server_ip,
     )]
     pub server_ip: Option<String>,
+}
 
+#[derive(clap::Args)]
+pub struct ArchiveArgs {
     #[clap(
-        short,
+        short = 'd',
         long,
-        help_heading = "Flags",
-        help = "Use a configuration file (config.yaml)\nto read application settings\n"
+        takes_value = true,
+        required = false,
+        default_value = ".",
+        help = "Directory where captures are written and\nserved from. The server and the observer\n\
+        can run separately on a shared directory\n"
     )]
-    pub config_file: bool,
+    pub capture_dir: String,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[clap(about = "Capture the url at each interval without starting the web server")]
+    Observe {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        capture: CaptureArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "5",
+            help = "Interval in minutes between each browser\ncapture (1 to 1440)\n",
+            value_parser = parse_interval,
+        )]
+        interval: u16,
+    },
+
+    #[clap(about = "Serve existing captures without capturing")]
+    Serve {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        server: ServerArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "jpeg",
+            help = "File format of captures (jpeg/png)\n",
+            value_parser = parse_file_format,
+        )]
+        file_format: String,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "5",
+            help = "Interval in minutes between each preview\nrefresh (1 to 1440)\n",
+            value_parser = parse_interval,
+        )]
+        interval: u16,
+    },
+
+    #[clap(about = "Capture the url at each interval and serve the captures")]
+    Run {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        capture: CaptureArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "5",
+            help = "Interval in minutes between each browser\ncapture (1 to 1440)\n",
+            value_parser = parse_interval,
+        )]
+        interval: u16,
+
+        #[clap(flatten)]
+        server: ServerArgs,
+    },
+
+    #[clap(about = "Take a single capture of the url and exit")]
+    Capture {
+        #[clap(flatten)]
+        capture: CaptureArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            help = "Capture file, its extension sets the file\nformat when it is png, jpeg or jpg\n\
+            (default: ./observed.<file format>)"
+        )]
+        out: Option<String>,
+    },
+
+    #[clap(about = "List the captures of the working directory")]
+    List {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "jpeg",
+            help = "File format of captures (jpeg/png)\n",
+            value_parser = parse_file_format,
+        )]
+        file_format: String,
+    },
+
+    #[clap(about = "Compare two captures and write an image of their differences")]
+    Diff {
+        #[clap(help = "Reference capture")]
+        baseline: String,
+
+        #[clap(help = "Capture compared to the reference")]
+        current: String,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            default_value = "./observed_diff.png",
+            help = "Diff image (png)\n"
+        )]
+        out: String,
+
+        #[clap(
+            long,
+            takes_value = true,
+            default_value = "16",
+            help = "Per channel difference under which two\npixels are considered equal\n"
+        )]
+        pixel_threshold: u8,
+    },
 
     #[clap(
-        short = 'b',
-        long,
-        takes_value = true,
-        required = false,
-        default_value = "chrome",
-        help = "Target browser used to capture the website.\nAvailable targets are: chromium, \
-        chrome, edge\n",
-        value_parser = parse_target_browser,
+        about = "Copy the captures, series, metadata, reports and baselines of the working \
+        directory to another directory"
     )]
-    pub target_browser: String,
+    Export {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(help = "Destination directory")]
+        out: String,
+    },
+
+    #[clap(about = "Launch observer's GUI")]
+    Gui,
+
+    #[clap(
+        about = "Capture every target once, compare the captures to their approved baselines \
+        and exit with a non-zero status on regressions"
+    )]
+    Test {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        capture: CaptureArgs,
+    },
+
+    #[clap(
+        about = "Promote the captures of the last test run to baselines (a target name \
+        approves all of its matrix combinations)"
+    )]
+    Approve {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(help = "Target or test case name")]
+        name: String,
+
+        #[clap(
+            long,
+            help = "Name recorded in the baseline history\n(default: current user)"
+        )]
+        by: Option<String>,
+    },
+
+    #[clap(about = "Restore an older baseline")]
+    Rollback {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(help = "Test case name")]
+        name: String,
+
+        #[clap(help = "Baseline version (default: the one approved\nbefore the current baseline)")]
+        version: Option<String>,
+
+        #[clap(
+            long,
+            help = "Name recorded in the baseline history\n(default: current user)"
+        )]
+        by: Option<String>,
+    },
+
+    #[clap(about = "Inspect the configuration")]
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+
+    #[clap(about = "List the baseline history of a test case")]
+    History {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(help = "Test case name")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    #[clap(
+        about = "Print the effective configuration and where each value comes from \
+        (default < configuration file < OBSERVER_* environment variables < command line flags)"
+    )]
+    Show {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        capture: CaptureArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "5",
+            help = "Interval in minutes between each browser\ncapture (1 to 1440)\n",
+            value_parser = parse_interval,
+        )]
+        interval: u16,
+
+        #[clap(flatten)]
+        server: ServerArgs,
+    },
+
+    #[clap(
+        about = "Check the configuration and list every problem found (unknown keys, bad types, \
+        invalid values) with its key and line, exit with a non-zero status if there are any"
+    )]
+    Check {
+        #[clap(flatten)]
+        archive: ArchiveArgs,
+
+        #[clap(flatten)]
+        capture: CaptureArgs,
+
+        #[clap(
+            short,
+            long,
+            takes_value = true,
+            required = false,
+            default_value = "5",
+            help = "Interval in minutes between each browser\ncapture (1 to 1440)\n",
+            value_parser = parse_interval,
+        )]
+        interval: u16,
+
+        #[clap(flatten)]
+        server: ServerArgs,
+    },
+
+    #[clap(
+        about = "Print the JSON Schema of the configuration file, for editors and YAML language \
+        servers"
+    )]
+    Schema,
 }
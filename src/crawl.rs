@@ -0,0 +1,393 @@
+use crate::inject::apply_injection;
+use crate::matrix::DEFAULT_VIEWPORT;
+use crate::observer::{catch_browser_panic, open_tab, screenshot_format};
+use crate::target::Target;
+use headless_chrome::Tab;
+use regex::Regex;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use url::Url;
+
+pub const CRAWL_DIR: &str = "observed_crawl";
+pub const CRAWL_INDEX_FILE: &str = "observed_crawl/index.json";
+
+const LINKS_JS: &str =
+    "JSON.stringify(Array.from(document.querySelectorAll('a[href]'), a => a.href))";
+const SITEMAP_PAGES_JS: &str = "JSON.stringify(Array.from(document.querySelectorAll('url > loc'), \
+    l => l.textContent.trim()))";
+const SITEMAP_INDEX_JS: &str = "JSON.stringify(Array.from(\
+    document.querySelectorAll('sitemap > loc'), l => l.textContent.trim()))";
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Crawl {
+    #[schemars(description = "Maximum number of links followed from the url")]
+    pub max_depth: u32,
+    #[schemars(description = "Maximum number of pages captured")]
+    #[schemars(range(min = 1))]
+    pub max_pages: u32,
+    #[schemars(description = "Regular expressions, only matching urls are crawled")]
+    pub include: Vec<String>,
+    #[schemars(description = "Regular expressions, matching urls are not crawled")]
+    pub exclude: Vec<String>,
+    #[schemars(description = "Also crawl the urls listed in /sitemap.xml")]
+    pub sitemap: bool,
+}
+
+impl Default for Crawl {
+    fn default() -> Self {
+        Self {
+            max_depth: 2,
+            max_pages: 50,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            sitemap: false,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CrawledPage {
+    pub url: String,
+    pub depth: u32,
+    pub name: String,
+    pub timestamp: String,
+}
+
+pub fn parse_crawl(c: &Crawl) -> Result<(), (String, String)> {
+    if c.max_pages == 0 {
+        return Err((
+            "max_pages".to_owned(),
+            "crawl max_pages must be at least 1".to_owned(),
+        ));
+    }
+
+    for (key, patterns) in [("include", &c.include), ("exclude", &c.exclude)] {
+        for (i, pattern) in patterns.iter().enumerate() {
+            if let Err(e) = Regex::new(pattern) {
+                return Err((
+                    format!("{}[{}]", key, i),
+                    format!("crawl pattern '{}' is invalid\n{}", pattern, e),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn crawl_capture_filename(name: &str, file_format: &str) -> String {
+    format!("{}/{}.{}", CRAWL_DIR, name, file_format)
+}
+
+struct Filter {
+    origin: url::Origin,
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl Filter {
+    fn new(start: &Url, crawl: &Crawl) -> anyhow::Result<Self> {
+        let compile = |patterns: &[String]| -> anyhow::Result<Vec<Regex>> {
+            Ok(patterns
+                .iter()
+                .map(|p| Regex::new(p))
+                .collect::<Result<_, _>>()?)
+        };
+
+        Ok(Self {
+            origin: start.origin(),
+            include: compile(&crawl.include)?,
+            exclude: compile(&crawl.exclude)?,
+        })
+    }
+
+    fn allows(&self, url: &Url) -> bool {
+        url.origin() == self.origin
+            && (self.include.is_empty() || self.include.iter().any(|r| r.is_match(url.as_str())))
+            && !self.exclude.iter().any(|r| r.is_match(url.as_str()))
+    }
+}
+
+// breadth-first crawl from the target url, capturing every page
+// reached within the depth and page limits into CRAWL_DIR of dir, pages
+// failing to load or capture are skipped and returned as warnings
+pub fn crawl_site(
+    dir: &Path,
+    target: &Target,
+    crawl: &Crawl,
+    file_format: &str,
+) -> anyhow::Result<(Vec<CrawledPage>, Vec<String>)> {
+    let mut start = Url::parse(&target.url)?;
+    start.set_fragment(None);
+    let filter = Filter::new(&start, crawl)?;
+
+    catch_browser_panic(&target.url, || {
+        let (_browser, tab) = open_tab(target, DEFAULT_VIEWPORT)?;
+        let mut warnings = Vec::new();
+        let mut seen = HashSet::from([start.to_string()]);
+        let mut queue = VecDeque::from([(start.clone(), 0)]);
+        if crawl.sitemap {
+            let sitemap = start.join("/sitemap.xml")?;
+            for url in sitemap_pages(&tab, &sitemap, &mut warnings) {
+                if filter.allows(&url) && seen.insert(url.to_string()) {
+                    queue.push_back((url, 0));
+                }
+            }
+        }
+
+        fs::create_dir_all(dir.join(CRAWL_DIR))?;
+        let fmt = screenshot_format(file_format)?;
+        let mut pages = Vec::new();
+        let mut names = HashSet::new();
+        while let Some((url, depth)) = queue.pop_front() {
+            if pages.len() >= crawl.max_pages as usize {
+                break;
+            }
+
+            let name = page_name(&url, &mut names);
+            let capture = || -> anyhow::Result<Vec<Url>> {
+                navigate(&tab, &url)?;
+                let links = listed_urls(&tab, LINKS_JS)?;
+                apply_injection(&tab, &target.inject)?;
+                let image_data = tab.capture_screenshot(fmt.clone(), Some(100), None, false)?;
+                fs::write(
+                    dir.join(crawl_capture_filename(&name, file_format)),
+                    image_data,
+                )?;
+                Ok(links)
+            };
+            let links = match capture() {
+                Ok(links) => links,
+                Err(e) => {
+                    warnings.push(format!("Crawl of {} failed, page skipped\n{}", url, e));
+                    continue;
+                }
+            };
+            pages.push(CrawledPage {
+                url: url.to_string(),
+                depth,
+                name,
+                timestamp: chrono::offset::Local::now().to_rfc3339(),
+            });
+
+            if depth < crawl.max_depth {
+                for link in links {
+                    if filter.allows(&link) && seen.insert(link.to_string()) {
+                        queue.push_back((link, depth + 1));
+                    }
+                }
+            }
+        }
+
+        remove_stale_captures(dir, &pages, file_format)?;
+        fs::write(dir.join(CRAWL_INDEX_FILE), serde_json::to_string(&pages)?)?;
+
+        Ok((pages, warnings))
+    })
+}
+
+// captures of pages no longer reached by the crawl would otherwise stay in CRAWL_DIR
+fn remove_stale_captures(dir: &Path, pages: &[CrawledPage], file_format: &str) -> io::Result<()> {
+    let current: Vec<String> = pages
+        .iter()
+        .map(|p| format!("{}.{}", p.name, file_format))
+        .collect();
+    for entry in fs::read_dir(dir.join(CRAWL_DIR))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name != "index.json" && !current.contains(&name) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn read_crawl_index(dir: &Path) -> Vec<CrawledPage> {
+    fs::read_to_string(dir.join(CRAWL_INDEX_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+// the pages listed by the sitemap, a sitemap index lists sitemaps
+// instead of pages and cannot list other indexes
+fn sitemap_pages(tab: &Tab, sitemap: &Url, warnings: &mut Vec<String>) -> Vec<Url> {
+    let listed = navigate(tab, sitemap).and_then(|_| {
+        Ok((
+            listed_urls(tab, SITEMAP_PAGES_JS)?,
+            listed_urls(tab, SITEMAP_INDEX_JS)?,
+        ))
+    });
+    let (mut pages, sitemaps) = match listed {
+        Ok(listed) => listed,
+        Err(e) => {
+            warnings.push(format!("Sitemap {} skipped\n{}", sitemap, e));
+            return Vec::new();
+        }
+    };
+
+    for sitemap in sitemaps {
+        match navigate(tab, &sitemap).and_then(|_| listed_urls(tab, SITEMAP_PAGES_JS)) {
+            Ok(urls) => pages.extend(urls),
+            Err(e) => warnings.push(format!("Sitemap {} skipped\n{}", sitemap, e)),
+        }
+    }
+
+    pages
+}
+
+fn navigate(tab: &Tab, url: &Url) -> anyhow::Result<()> {
+    tab.navigate_to(url.as_str())
+        .and_then(|t| t.wait_until_navigated())?;
+
+    Ok(())
+}
+
+// the http(s) urls listed by the script in the loaded page, without fragments
+fn listed_urls(tab: &Tab, script: &str) -> anyhow::Result<Vec<Url>> {
+    let value = tab.evaluate(script, false)?.value;
+    parse_urls(value.as_ref().and_then(|v| v.as_str()))
+}
+
+// the scripts return the urls as a JSON array of strings
+fn parse_urls(json: Option<&str>) -> anyhow::Result<Vec<Url>> {
+    let urls: Vec<String> = match json {
+        Some(json) => serde_json::from_str(json)?,
+        None => Vec::new(),
+    };
+
+    Ok(urls
+        .iter()
+        .filter_map(|u| Url::parse(u).ok())
+        .filter(|u| u.scheme() == "http" || u.scheme() == "https")
+        .map(|mut u| {
+            u.set_fragment(None);
+            u
+        })
+        .collect())
+}
+
+// filesystem safe name derived from the url path and query, unique within a crawl
+fn page_name(url: &Url, names: &mut HashSet<String>) -> String {
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+    let mut base: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('_')
+        .chars()
+        .take(100)
+        .collect();
+    if base.is_empty() {
+        base = "index".to_owned();
+    }
+
+    let mut name = base.clone();
+    let mut i = 2;
+    while !names.insert(name.clone()) {
+        name = format!("{}-{}", base, i);
+        i += 1;
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(u: &str) -> Url {
+        Url::parse(u).unwrap()
+    }
+
+    #[test]
+    fn page_names() {
+        let mut names = HashSet::new();
+        for (u, expected) in [
+            ("https://www.gnu.org/", "index"),
+            ("https://www.gnu.org/software/emacs/", "software_emacs"),
+            ("https://www.gnu.org/software/emacs", "software_emacs-2"),
+            ("https://www.gnu.org/search?q=gnu&p=2", "search_q_gnu_p_2"),
+            ("https://www.gnu.org/?lang=fr", "lang_fr"),
+            ("https://www.gnu.org", "index-2"),
+        ] {
+            assert_eq!(page_name(&url(u), &mut names), expected, "{}", u);
+        }
+
+        let long = format!("https://www.gnu.org/{}", "a".repeat(300));
+        assert_eq!(page_name(&url(&long), &mut names).len(), 100);
+    }
+
+    #[test]
+    fn filter() {
+        let crawl = Crawl {
+            include: vec!["/docs/".to_owned()],
+            exclude: vec!["\\.pdf$".to_owned()],
+            ..Default::default()
+        };
+        let filter = Filter::new(&url("https://www.gnu.org/docs/"), &crawl).unwrap();
+        assert!(filter.allows(&url("https://www.gnu.org/docs/manual.html")));
+        assert!(!filter.allows(&url("https://www.gnu.org/docs/manual.pdf")));
+        assert!(!filter.allows(&url("https://www.gnu.org/software/")));
+        assert!(!filter.allows(&url("http://www.gnu.org/docs/manual.html")));
+        assert!(!filter.allows(&url("https://gnu.org/docs/manual.html")));
+
+        let everything = Filter::new(&url("https://www.gnu.org/"), &Crawl::default()).unwrap();
+        assert!(everything.allows(&url("https://www.gnu.org/software/")));
+
+        let invalid = Crawl {
+            exclude: vec!["(".to_owned()],
+            ..Default::default()
+        };
+        assert!(Filter::new(&url("https://www.gnu.org/"), &invalid).is_err());
+        assert!(parse_crawl(&invalid).is_err());
+    }
+
+    #[test]
+    fn listed_urls() {
+        // as returned for the <loc> of a sitemap or sitemap index
+        let urls = parse_urls(Some(
+            r#"["https://www.gnu.org/a#top", "mailto:gnu@gnu.org", "not a url",
+            "http://www.gnu.org/sitemap-2.xml"]"#,
+        ))
+        .unwrap();
+        let urls: Vec<&str> = urls.iter().map(Url::as_str).collect();
+        assert_eq!(
+            urls,
+            ["https://www.gnu.org/a", "http://www.gnu.org/sitemap-2.xml"]
+        );
+        assert!(parse_urls(None).unwrap().is_empty());
+        assert!(parse_urls(Some("<urlset>")).is_err());
+    }
+
+    #[test]
+    fn stale_captures() {
+        let dir = std::env::temp_dir().join(format!("observer_crawl_{}", std::process::id()));
+        fs::create_dir_all(dir.join(CRAWL_DIR)).unwrap();
+        for file in ["index.png", "old.png", "index.jpeg", "index.json"] {
+            fs::write(dir.join(CRAWL_DIR).join(file), "").unwrap();
+        }
+        let pages = [CrawledPage {
+            url: "https://www.gnu.org/".to_owned(),
+            depth: 0,
+            name: "index".to_owned(),
+            timestamp: String::new(),
+        }];
+        remove_stale_captures(&dir, &pages, "png").unwrap();
+
+        let mut files: Vec<String> = fs::read_dir(dir.join(CRAWL_DIR))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["index.json", "index.png"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use crate::metadata::{CaptureMetadata, ConsoleMessage, FailedRequest};
+use headless_chrome::protocol::cdp::types::Event;
+use headless_chrome::protocol::cdp::Network::ResourceType;
+use headless_chrome::protocol::cdp::Runtime::{ConsoleAPICalledEventTypeOption, RemoteObject};
+use headless_chrome::Tab;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Diagnostics {
+    console: Vec<ConsoleMessage>,
+    exceptions: Vec<String>,
+    failed_requests: Vec<FailedRequest>,
+    request_urls: HashMap<String, String>,
+}
+
+// records console messages, uncaught exceptions and failed subresource requests
+// of a tab from CDP Runtime and Network events (Network must already be enabled)
+pub struct DiagnosticsRecorder(Arc<Mutex<Diagnostics>>);
+impl DiagnosticsRecorder {
+    pub fn attach(tab: &Tab) -> anyhow::Result<Self> {
+        tab.enable_runtime()?;
+
+        let diagnostics = Arc::new(Mutex::new(Diagnostics::default()));
+        let main_frame = tab.get_target_id().clone();
+        let recorder = diagnostics.clone();
+        tab.add_event_listener(Arc::new(move |event: &Event| {
+            let mut d = recorder.lock().expect("Locking diagnostics");
+            match event {
+                Event::RuntimeConsoleAPICalled(e) => {
+                    let level = match e.params.Type {
+                        ConsoleAPICalledEventTypeOption::Error
+                        | ConsoleAPICalledEventTypeOption::Assert => "error",
+                        ConsoleAPICalledEventTypeOption::Warning => "warning",
+                        ConsoleAPICalledEventTypeOption::Info => "info",
+                        ConsoleAPICalledEventTypeOption::Debug => "debug",
+                        _ => "log",
+                    };
+                    let text = e
+                        .params
+                        .args
+                        .iter()
+                        .map(remote_object_to_string)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    d.console.push(ConsoleMessage {
+                        level: level.to_owned(),
+                        text,
+                    });
+                }
+                Event::RuntimeExceptionThrown(e) => {
+                    let details = &e.params.exception_details;
+                    let text = details
+                        .exception
+                        .as_ref()
+                        .and_then(|ex| ex.description.clone())
+                        .unwrap_or_else(|| details.text.clone());
+                    d.exceptions.push(text);
+                }
+                Event::NetworkRequestWillBeSent(e) => {
+                    if e.params.Type == Some(ResourceType::Document)
+                        && e.params.frame_id.as_ref() == Some(&main_frame)
+                        && e.params.redirect_response.is_none()
+                    {
+                        // a new navigation starts from scratch
+                        *d = Diagnostics::default();
+                    }
+
+                    d.request_urls
+                        .insert(e.params.request_id.clone(), e.params.request.url.clone());
+                }
+                Event::NetworkResponseReceived(e)
+                    if e.params.response.status >= 400
+                        && !(e.params.Type == ResourceType::Document
+                            && e.params.frame_id.as_ref() == Some(&main_frame)) =>
+                {
+                    d.failed_requests.push(FailedRequest {
+                        url: e.params.response.url.clone(),
+                        status: Some(e.params.response.status),
+                        error: None,
+                    });
+                }
+                Event::NetworkLoadingFailed(e) if e.params.canceled != Some(true) => {
+                    let url = d
+                        .request_urls
+                        .get(&e.params.request_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    d.failed_requests.push(FailedRequest {
+                        url,
+                        status: None,
+                        error: Some(e.params.error_text.clone()),
+                    });
+                }
+                _ => (),
+            }
+        }))?;
+
+        Ok(Self(diagnostics))
+    }
+
+    pub fn fill(&self, metadata: &mut CaptureMetadata) {
+        let d = self.0.lock().expect("Locking diagnostics");
+        metadata.console = d.console.clone();
+        metadata.exceptions = d.exceptions.clone();
+        metadata.failed_requests = d.failed_requests.clone();
+    }
+}
+
+fn remote_object_to_string(o: &RemoteObject) -> String {
+    match &o.value {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        None => o.description.clone().unwrap_or_default(),
+    }
+}
+
+// compare errors against the previous capture so that only new ones are reported
+pub fn new_errors(previous: Option<&CaptureMetadata>, current: &CaptureMetadata) -> Vec<String> {
+    let previous = previous.map(error_list).unwrap_or_default();
+    error_list(current)
+        .into_iter()
+        .filter(|e| !previous.contains(e))
+        .collect()
+}
+
+fn error_list(m: &CaptureMetadata) -> Vec<String> {
+    let console = m
+        .console
+        .iter()
+        .filter(|c| c.level == "error")
+        .map(|c| format!("console error: {}", c.text));
+    let exceptions = m
+        .exceptions
+        .iter()
+        .map(|e| format!("uncaught exception: {}", e));
+    let requests = m
+        .failed_requests
+        .iter()
+        .map(|r| match (&r.status, &r.error) {
+            (Some(status), _) => format!("request failed ({}): {}", status, r.url),
+            (None, Some(error)) => format!("request failed ({}): {}", error, r.url),
+            (None, None) => format!("request failed: {}", r.url),
+        });
+
+    console.chain(exceptions).chain(requests).collect()
+}
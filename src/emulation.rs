@@ -0,0 +1,153 @@
+use headless_chrome::protocol::cdp::Browser::{GetVersion, GrantPermissions, PermissionType};
+use headless_chrome::protocol::cdp::Emulation::{
+    MediaFeature, SetEmulatedMedia, SetGeolocationOverride, SetLocaleOverride, SetTimezoneOverride,
+};
+use headless_chrome::Tab;
+
+pub const COLOR_SCHEMES: [&str; 2] = ["light", "dark"];
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Emulation {
+    #[schemars(description = "Preferred color scheme")]
+    #[schemars(schema_with = "crate::schema::color_scheme")]
+    pub color_scheme: Option<String>,
+    #[schemars(description = "Prefer reduced motion")]
+    pub reduced_motion: bool,
+    #[schemars(description = "Locale (ex: en-US)")]
+    pub locale: Option<String>,
+    #[schemars(description = "Accept-Language header")]
+    pub accept_language: Option<String>,
+    #[schemars(description = "Timezone (ex: Europe/Paris)")]
+    pub timezone: Option<String>,
+    #[schemars(description = "Geolocation, the permission is granted to the page")]
+    pub geolocation: Option<Geolocation>,
+    #[schemars(description = "User agent")]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+pub struct Geolocation {
+    #[schemars(description = "Latitude in degrees")]
+    #[schemars(range(min = -90, max = 90))]
+    pub latitude: f64,
+    #[schemars(description = "Longitude in degrees")]
+    #[schemars(range(min = -180, max = 180))]
+    pub longitude: f64,
+    #[serde(default = "default_accuracy")]
+    #[schemars(description = "Accuracy in meters")]
+    pub accuracy: f64,
+}
+
+fn default_accuracy() -> f64 {
+    10.0
+}
+
+impl Emulation {
+    pub fn is_empty(&self) -> bool {
+        self.color_scheme.is_none()
+            && !self.reduced_motion
+            && self.locale.is_none()
+            && self.accept_language.is_none()
+            && self.timezone.is_none()
+            && self.geolocation.is_none()
+            && self.user_agent.is_none()
+    }
+}
+
+pub fn parse_emulation(e: &Emulation) -> Result<(), (String, String)> {
+    if let Some(scheme) = &e.color_scheme {
+        if !COLOR_SCHEMES.contains(&&*scheme.to_lowercase()) {
+            return Err((
+                "color_scheme".to_owned(),
+                "color scheme must either be light or dark".to_owned(),
+            ));
+        }
+    }
+
+    if let Some(locale) = &e.locale {
+        if locale.is_empty()
+            || !locale
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err((
+                "locale".to_owned(),
+                format!("locale '{}' must follow this format: 'en-US'", locale),
+            ));
+        }
+    }
+
+    if let Some(g) = &e.geolocation {
+        if !(-90.0..=90.0).contains(&g.latitude) || !(-180.0..=180.0).contains(&g.longitude) {
+            return Err((
+                "geolocation".to_owned(),
+                "geolocation latitude must be within [-90, 90] and \
+                longitude within [-180, 180]"
+                    .to_owned(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// must be applied before navigation
+pub fn apply_emulation(tab: &Tab, e: &Emulation) -> anyhow::Result<()> {
+    let mut features = Vec::new();
+    if let Some(scheme) = &e.color_scheme {
+        features.push(MediaFeature {
+            name: "prefers-color-scheme".to_owned(),
+            value: scheme.to_lowercase(),
+        });
+    }
+    if e.reduced_motion {
+        features.push(MediaFeature {
+            name: "prefers-reduced-motion".to_owned(),
+            value: "reduce".to_owned(),
+        });
+    }
+    if !features.is_empty() {
+        tab.call_method(SetEmulatedMedia {
+            media: None,
+            features: Some(features),
+        })?;
+    }
+
+    if let Some(locale) = &e.locale {
+        tab.call_method(SetLocaleOverride {
+            locale: Some(locale.clone()),
+        })?;
+    }
+
+    if let Some(timezone) = &e.timezone {
+        tab.call_method(SetTimezoneOverride {
+            timezone_id: timezone.clone(),
+        })?;
+    }
+
+    if let Some(g) = &e.geolocation {
+        tab.call_method(GrantPermissions {
+            permissions: vec![PermissionType::Geolocation],
+            origin: None,
+            browser_context_id: None,
+        })?;
+        tab.call_method(SetGeolocationOverride {
+            latitude: Some(g.latitude),
+            longitude: Some(g.longitude),
+            accuracy: Some(g.accuracy),
+        })?;
+    }
+
+    // the Accept-Language header can only be overridden together with the user agent
+    let accept_language = e.accept_language.as_ref().or(e.locale.as_ref());
+    if e.user_agent.is_some() || accept_language.is_some() {
+        let user_agent = match &e.user_agent {
+            Some(ua) => ua.clone(),
+            None => tab.call_method(GetVersion(None))?.user_agent,
+        };
+        tab.set_user_agent(&user_agent, accept_language.map(|l| l.as_str()), None)?;
+    }
+
+    Ok(())
+}
@@ -1,16 +1,19 @@
 use gtk::glib::{timeout_add_seconds_local, Char, OptionArg, OptionFlags};
 use gtk::prelude::*;
 use gtk::{glib, Align, Application, ApplicationWindow, Orientation};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::mpsc;
 
 use crate::observer::start_observing;
-use crate::server::start_server;
+use crate::reload::observed_targets;
+use crate::server::{server_address, start_server, ServerOptions};
 use crate::settings::{
-    load_config_gui_mode, parse_config_gui_mode, parse_file_format, parse_host, parse_server_ip,
-    parse_target_browser, parse_url, Settings,
+    load_config_gui_mode, parse_config_gui_mode, parse_file_format, parse_host, parse_listeners,
+    parse_server_ip, parse_target_browser, parse_url, Settings,
 };
+use crate::target::Target;
 
 pub fn start_gui() {
     let app = Application::builder()
@@ -105,7 +108,7 @@ fn build_ui(app: &Application) {
     observer_box.append(&target_browser);
 
     let server_ip = gtk::Entry::builder()
-        .placeholder_text("192.168.13.37")
+        .placeholder_text("detected if empty")
         .build();
     server_box.append(&gtk::Label::new(Some("Server IP")));
     server_box.append(&server_ip);
@@ -235,7 +238,9 @@ fn build_ui(app: &Application) {
     }));
 
     let (status_msg_tx, status_msg_rx) = mpsc::channel();
-    let (observer_stop_tx, observer_stop_rx) = crossbeam_channel::bounded(1);
+    // one stop message per running observer (the configuration can list several targets)
+    let (observer_stop_tx, observer_stop_rx) = crossbeam_channel::unbounded();
+    let observer_count = Rc::new(Cell::new(0));
     observer_button.connect_clicked(glib::clone!(
         @weak load_config_ch_btn,
         @weak settings,
@@ -253,7 +258,7 @@ fn build_ui(app: &Application) {
                         inter = settings.borrow().interval;
                         [
                             settings.borrow().target_browser.clone(),
-                            settings.borrow().url.clone(),
+                            settings.borrow().target.url.clone(),
                             settings.borrow().file_format.clone(),
                         ]
                     },
@@ -277,27 +282,57 @@ fn build_ui(app: &Application) {
                     return;
                 }
 
-                let status_msg_tx_c = status_msg_tx.clone();
-                let observer_stop_rx_c = observer_stop_rx.clone();
-                std::thread::spawn(move || {
-                    if let Err(e) = start_observing(
-                        &config[0],
-                        &config[1],
-                        &config[2],
-                        inter,
-                        quiet_flag,
-                        Some((&status_msg_tx_c, &observer_stop_rx_c)),
-                    ) {
-                        status_msg_tx_c
-                            .send(format!("[err-obs]{}", e))
-                            .expect("Sending Observer error through mpsc channel");
+                // like observe, every target of the configuration file is observed into
+                // its own directory of the capture directory
+                let targets: Vec<(Target, PathBuf)> = match load_config_is_active {
+                    true => observed_targets(&settings.borrow(), settings.borrow().capture_path())
+                        .into_iter()
+                        .map(|(t, dir)| (t.clone(), dir))
+                        .collect(),
+                    false => {
+                        let target = Target {
+                            url: config[1].clone(),
+                            ..Default::default()
+                        };
+                        vec![(target, PathBuf::from("."))]
                     }
-                });
+                };
+
+                // stop messages left by observers that stopped on an error
+                while observer_stop_rx.try_recv().is_ok() {}
+                observer_count.set(targets.len());
+                for (target, dir) in targets {
+                    let status_msg_tx_c = status_msg_tx.clone();
+                    let observer_stop_rx_c = observer_stop_rx.clone();
+                    let (target_browser, file_format) = (config[0].clone(), config[2].clone());
+                    std::thread::spawn(move || {
+                        let observed = std::fs::create_dir_all(&dir)
+                            .map_err(|e| anyhow::anyhow!("Error: {}: {}", dir.display(), e))
+                            .and_then(|_| {
+                                start_observing(
+                                    &target_browser,
+                                    &target,
+                                    &dir,
+                                    &file_format,
+                                    inter,
+                                    quiet_flag,
+                                    Some((&status_msg_tx_c, &observer_stop_rx_c)),
+                                )
+                            });
+                        if let Err(e) = observed {
+                            status_msg_tx_c
+                                .send(format!("[err-obs]{}", e))
+                                .expect("Sending Observer error through mpsc channel");
+                        }
+                    });
+                }
                 observer_button.set_label("Stop Observer")
             }
             "Stop Observer" => {
                 observer_button.set_label("Start Observer");
-                observer_stop_tx.send(()).expect("Stopping Observer with crossbeam channel");
+                for _ in 0..observer_count.get() {
+                    observer_stop_tx.send(()).expect("Stopping Observer with crossbeam channel");
+                }
             }
             _ => (),
         };
@@ -332,6 +367,10 @@ fn build_ui(app: &Application) {
                     },
                 };
 
+                // the server IP is only printed, detected when left empty
+                if config[0].trim().is_empty() {
+                    config[0] = server_address("", config[1].trim());
+                }
                 if let Err(e) = parse_config_gui_mode(
                     &mut config,
                     [parse_server_ip, parse_host, parse_file_format],
@@ -342,16 +381,44 @@ fn build_ui(app: &Application) {
                     return;
                 }
 
+                let listeners = match parse_listeners(&config[1], nums.0) {
+                    Ok(l) => l,
+                    Err(e) => {
+                        append_status_msg(&status_msg, format!("Error: {}", e));
+                        return;
+                    }
+                };
+
+                // https, web approvals and the capture directory are only configured in the
+                // configuration file
+                let (tls, approve_token, capture_dir) = match load_config_is_active {
+                    true => {
+                        let settings = settings.borrow();
+                        (
+                            settings.tls.clone(),
+                            settings.approve_token.clone(),
+                            settings.capture_path().to_owned(),
+                        )
+                    }
+                    false => (None, None, PathBuf::from(".")),
+                };
+
                 let status_msg_tx_c = status_msg_tx.clone();
                 let server_stop_rx_c = server_stop_rx.clone();
                 std::thread::spawn(move || {
+                    let options = ServerOptions {
+                        server_ip: &config[0],
+                        listeners: &listeners,
+                        tls: tls.as_ref(),
+                        approve_token: approve_token.as_deref(),
+                        capture_dir: &capture_dir,
+                    };
                     if let Err(e) = start_server(
-                        &config[0],
-                        &config[1],
-                        nums.0,
+                        &options,
                         &config[2],
                         nums.1,
                         Some((&status_msg_tx_c, &server_stop_rx_c)),
+                        None,
                     ) {
                         status_msg_tx_c
                             .send(format!("[err-serv]{}", e))
@@ -0,0 +1,59 @@
+use headless_chrome::Tab;
+
+const DISABLE_ANIMATIONS_CSS: &str = "*, *::before, *::after { \
+    transition: none !important; \
+    animation: none !important; \
+    caret-color: transparent !important; \
+    scroll-behavior: auto !important; }";
+const DISABLE_ANIMATIONS_JS: &str =
+    "document.querySelectorAll('video, audio').forEach((m) => m.pause()); \
+    document.getAnimations().forEach((a) => { try { a.finish() } catch (e) { a.pause() } });";
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Inject {
+    #[schemars(description = "CSS added to the page")]
+    pub css: Option<String>,
+    #[schemars(description = "JavaScript evaluated in the page")]
+    pub js: Option<String>,
+    #[schemars(description = "Disable transitions and animations and pause media")]
+    pub disable_animations: bool,
+}
+
+impl Inject {
+    pub fn is_empty(&self) -> bool {
+        self.css.is_none() && self.js.is_none() && !self.disable_animations
+    }
+}
+
+// runs on a loaded page, must be applied again after each navigation
+pub fn apply_injection(tab: &Tab, inject: &Inject) -> anyhow::Result<()> {
+    let mut css = String::new();
+    if inject.disable_animations {
+        css.push_str(DISABLE_ANIMATIONS_CSS);
+    }
+    if let Some(c) = &inject.css {
+        css.push_str(c);
+    }
+
+    if !css.is_empty() {
+        tab.evaluate(
+            &format!(
+                "(() => {{ let s = document.createElement('style'); s.textContent = {}; \
+                document.head.appendChild(s); }})()",
+                serde_json::to_string(&css)?
+            ),
+            false,
+        )?;
+    }
+
+    if inject.disable_animations {
+        tab.evaluate(DISABLE_ANIMATIONS_JS, false)?;
+    }
+
+    if let Some(js) = &inject.js {
+        tab.evaluate(js, true)?;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,26 @@
-mod cli;
+pub mod archive;
+pub mod auth;
+pub mod baseline;
+pub mod checks;
+pub mod cli;
+pub mod crawl;
+pub mod diagnostics;
+pub mod emulation;
 pub mod gui;
+pub mod har;
+pub mod inject;
+pub mod matrix;
+pub mod metadata;
 pub mod observer;
+pub mod proxy;
+pub mod regression;
+pub mod reload;
+pub mod report;
+pub mod schema;
+pub mod series;
 pub mod server;
 pub mod settings;
+pub mod steps;
+pub mod target;
+pub mod tls;
+pub mod validation;
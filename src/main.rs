@@ -1,36 +1,198 @@
+use anyhow::{anyhow, bail};
+use observer::archive::{export_archive, list_captures};
+use observer::baseline::{approve, default_approver, read_history, rollback};
+use observer::cli::{Command, ConfigAction};
 use observer::gui;
-use observer::observer::start_observing;
-use observer::server::start_server;
-use observer::settings::Settings;
+use observer::matrix::DEFAULT_VIEWPORT;
+use observer::observer::capture_page;
+use observer::regression::{compare_images, run_test, TestStatus};
+use observer::reload::supervise;
+use observer::schema::config_schema;
+use observer::server::{start_server, ServerOptions};
+use observer::settings::{parse_file_format, parse_listeners, Settings};
+use observer::validation::check_config;
+use std::path::Path;
 
 fn main() -> anyhow::Result<()> {
-    let s = Settings::parse_settings()?;
-    if s.gui_mode {
+    let mut s = Settings::parse_settings()?;
+    // config commands inspect a configuration setting gui_mode instead of launching the GUI
+    if s.gui_mode && !matches!(s.command, Some(Command::Config { .. })) {
         gui::start_gui();
-    } else {
-        let ff = s.file_format.clone();
-        std::thread::spawn(move || {
-            if let Err(e) = start_observing(
+        return Ok(());
+    }
+
+    if !s.capture_dir.is_empty() {
+        std::fs::create_dir_all(&s.capture_dir)?;
+    }
+
+    let command = s
+        .command
+        .take()
+        .expect("Parsing the command with the settings");
+    if let Err(e) = run_command(command, s) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+// the server's errors are also shown by the GUI and do not start with "Error: "
+fn serve(s: Settings, observing: bool) -> anyhow::Result<()> {
+    let listeners = parse_listeners(&s.host, s.port).map_err(|e| anyhow!("Error: {}", e))?;
+    let (server_ip, tls) = (s.server_ip.clone(), s.tls.clone());
+    let (approve_token, capture_dir) = (s.approve_token.clone(), s.capture_path().to_owned());
+    let (file_format, interval) = (s.file_format.clone(), s.interval);
+    let (reload_tx, reload_rx) = crossbeam_channel::unbounded();
+    std::thread::spawn(move || {
+        if let Err(e) = supervise(s, observing, Some(reload_tx)) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    });
+
+    let options = ServerOptions {
+        server_ip: &server_ip,
+        listeners: &listeners,
+        tls: tls.as_ref(),
+        approve_token: approve_token.as_deref(),
+        capture_dir: &capture_dir,
+    };
+    start_server(&options, &file_format, interval, None, Some(reload_rx))
+        .map_err(|e| anyhow!("Error: {}", e))
+}
+
+// capture paths are resolved against the capture directory,
+// paths given on the command line against the working directory
+fn run_command(command: Command, s: Settings) -> anyhow::Result<()> {
+    let dir = s.capture_path().to_owned();
+    match &command {
+        Command::Observe { .. } => supervise(s, true, None)?,
+        Command::Serve { .. } => serve(s, false)?,
+        Command::Run { .. } => serve(s, true)?,
+        Command::Gui => gui::start_gui(),
+        Command::Test { .. } => {
+            let cases = run_test(
+                &dir,
                 &s.target_browser,
-                &s.url,
-                &ff,
-                s.interval,
+                &s.all_targets(),
+                &s.file_format,
                 s.quiet_flag,
-                None,
-            ) {
-                eprintln!("{e}");
+            )?;
+            if cases.iter().any(|c| c.status != TestStatus::Passed) {
                 std::process::exit(1);
             }
-        });
-
-        start_server(
-            &s.server_ip,
-            &s.host,
-            s.port,
-            &s.file_format,
-            s.interval,
-            None,
-        )?;
+        }
+        Command::Approve { name, by, .. } => {
+            let by = by.clone().unwrap_or_else(default_approver);
+            for entry in approve(&dir, name, &by)? {
+                println!(
+                    "{} | {} approved by {}",
+                    entry.version, entry.name, entry.by
+                );
+            }
+        }
+        Command::Rollback {
+            name, version, by, ..
+        } => {
+            let by = by.clone().unwrap_or_else(default_approver);
+            let entry = rollback(&dir, name, version.as_deref(), &by)?;
+            println!(
+                "{} | {} restored by {}",
+                entry.version, entry.name, entry.by
+            );
+        }
+        Command::Capture { out, .. } => {
+            let filename = match out {
+                Some(o) => o.clone(),
+                None => dir
+                    .join(format!("observed.{}", s.file_format))
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            let file_format = Path::new(&filename)
+                .extension()
+                .and_then(|e| parse_file_format(&e.to_string_lossy()).ok())
+                .unwrap_or_else(|| s.file_format.clone());
+            let (_, metadata) = capture_page(
+                &s.target_browser,
+                &s.target,
+                DEFAULT_VIEWPORT,
+                &file_format,
+                &filename,
+            )?;
+            if let Some(error) = metadata.error {
+                bail!("Error: {}", error);
+            }
+            if !s.quiet_flag {
+                println!(
+                    "{} | {} saved",
+                    chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y"),
+                    filename
+                );
+            }
+        }
+        Command::List { .. } => {
+            for (file, modified) in list_captures(&dir, &s.file_format)? {
+                let modified: chrono::DateTime<chrono::Local> = modified.into();
+                println!(
+                    "{} | {}",
+                    modified.format("%H:%M:%S-%d/%m/%Y"),
+                    file.display()
+                );
+            }
+        }
+        Command::Diff {
+            baseline,
+            current,
+            out,
+            pixel_threshold,
+        } => {
+            let percent = compare_images(baseline, current, *pixel_threshold, out)?;
+            println!("{:.3}% of pixels differ\n{} saved", percent, out);
+        }
+        Command::Export { out, .. } => {
+            let count = export_archive(&dir, Path::new(out))?;
+            if !s.quiet_flag {
+                println!("{} files exported to {}", count, out);
+            }
+        }
+        Command::Config {
+            action: ConfigAction::Show { .. },
+        } => {
+            if let Some(layers) = &s.layers {
+                println!("{}", layers.show()?);
+            }
+        }
+        Command::Config {
+            action: ConfigAction::Check { .. },
+        } => {
+            if let Some(layers) = &s.layers {
+                let issues = check_config(layers)?;
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                if !issues.is_empty() {
+                    std::process::exit(1);
+                }
+                if !s.quiet_flag {
+                    println!("Configuration is valid");
+                }
+            }
+        }
+        Command::Config {
+            action: ConfigAction::Schema,
+        } => {
+            println!("{}", serde_json::to_string_pretty(&config_schema()?)?);
+        }
+        Command::History { name, .. } => {
+            for entry in read_history(&dir, name) {
+                println!(
+                    "{} | {} by {} at {}",
+                    entry.version, entry.action, entry.by, entry.at
+                );
+            }
+        }
     }
 
     Ok(())
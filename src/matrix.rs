@@ -0,0 +1,171 @@
+use crate::emulation::COLOR_SCHEMES;
+use crate::observer::edge_unsupported;
+use crate::settings::parse_target_browser;
+use crate::steps::Step;
+use crate::target::Target;
+use std::path::Path;
+use std::{fs, io};
+
+pub const DEFAULT_VIEWPORT: (u32, u32) = (1920, 1080);
+pub const MATRIX_DIR: &str = "observed_matrix";
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Matrix {
+    #[schemars(description = "Viewports (ex: '1920x1080')")]
+    pub viewports: Vec<String>,
+    #[schemars(description = "Browsers, the target browser by default")]
+    #[schemars(schema_with = "crate::schema::browsers")]
+    pub browsers: Vec<String>,
+    #[schemars(description = "Color schemes")]
+    #[schemars(schema_with = "crate::schema::color_schemes")]
+    pub color_schemes: Vec<String>,
+}
+
+pub struct Combination {
+    pub browser: String,
+    pub viewport: (u32, u32),
+    pub color_scheme: Option<String>,
+}
+
+pub fn parse_matrix(m: &Matrix, t: &Target) -> Result<(), (String, String)> {
+    for (i, v) in m.viewports.iter().enumerate() {
+        parse_viewport(v).map_err(|e| (format!("viewports[{}]", i), e))?;
+    }
+
+    for (i, b) in m.browsers.iter().enumerate() {
+        parse_target_browser(b).map_err(|e| (format!("browsers[{}]", i), e))?;
+        if b.eq_ignore_ascii_case("edge") {
+            if let Some(feature) = edge_unsupported(t) {
+                return Err((
+                    format!("browsers[{}]", i),
+                    format!(
+                        "matrix browser edge: {} only supported with chromium and chrome targets",
+                        feature
+                    ),
+                ));
+            }
+        }
+    }
+
+    for (i, c) in m.color_schemes.iter().enumerate() {
+        if !COLOR_SCHEMES.contains(&&*c.to_lowercase()) {
+            return Err((
+                format!("color_schemes[{}]", i),
+                "matrix color schemes must either be light or dark".to_owned(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn parse_viewport(v: &str) -> Result<(u32, u32), String> {
+    let err = || format!("viewport '{}' must follow this format: '1920x1080'", v);
+    let (width, height) = v.trim().split_once('x').ok_or_else(err)?;
+    let width = width.parse().map_err(|_| err())?;
+    let height = height.parse().map_err(|_| err())?;
+    if width == 0 || height == 0 {
+        return Err(err());
+    }
+
+    Ok((width, height))
+}
+
+impl Matrix {
+    // dimensions left empty fall back to the default browser and viewport
+    pub fn combinations(&self, default_browser: &str) -> Vec<Combination> {
+        let browsers = match self.browsers.is_empty() {
+            true => vec![default_browser.to_lowercase()],
+            false => self.browsers.iter().map(|b| b.to_lowercase()).collect(),
+        };
+        let viewports = match self.viewports.is_empty() {
+            true => vec![DEFAULT_VIEWPORT],
+            false => self
+                .viewports
+                .iter()
+                .filter_map(|v| parse_viewport(v).ok())
+                .collect(),
+        };
+        let color_schemes = match self.color_schemes.is_empty() {
+            true => vec![None],
+            false => self
+                .color_schemes
+                .iter()
+                .map(|c| Some(c.to_lowercase()))
+                .collect(),
+        };
+
+        let mut combinations = Vec::new();
+        for browser in &browsers {
+            for viewport in &viewports {
+                for color_scheme in &color_schemes {
+                    combinations.push(Combination {
+                        browser: browser.clone(),
+                        viewport: *viewport,
+                        color_scheme: color_scheme.clone(),
+                    });
+                }
+            }
+        }
+
+        combinations
+    }
+}
+
+impl Combination {
+    // browser_WIDTHxHEIGHT[_scheme], also used by the server to lay out the grid
+    pub fn name(&self) -> String {
+        let mut name = format!("{}_{}x{}", self.browser, self.viewport.0, self.viewport.1);
+        if let Some(c) = &self.color_scheme {
+            name = format!("{}_{}", name, c);
+        }
+
+        name
+    }
+
+    pub fn variant(&self, target: &Target) -> Target {
+        let mut variant = target.clone();
+        if self.color_scheme.is_some() {
+            variant.emulation.color_scheme = self.color_scheme.clone();
+        }
+
+        // only the main capture records HAR files, values, named captures and crawls
+        variant.har = false;
+        variant.extractors.clear();
+        variant.steps.retain(|s| !matches!(s, Step::Screenshot(_)));
+        variant.matrix = None;
+        variant.crawl = None;
+
+        variant
+    }
+}
+
+pub fn matrix_capture_filename(name: &str, file_format: &str) -> String {
+    format!("{}/{}.{}", MATRIX_DIR, name, file_format)
+}
+
+// captures of combinations no longer in the matrix would otherwise stay in the grid
+pub fn remove_stale_captures(
+    dir: &Path,
+    combinations: &[Combination],
+    file_format: &str,
+) -> io::Result<()> {
+    let current: Vec<String> = combinations
+        .iter()
+        .map(|c| format!("{}.{}", c.name(), file_format))
+        .collect();
+    let entries = match fs::read_dir(dir.join(MATRIX_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if !current.contains(&entry.file_name().to_string_lossy().into_owned()) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
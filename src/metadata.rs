@@ -0,0 +1,69 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const METADATA_FILE: &str = "observed.json";
+pub const METADATA_HISTORY_FILE: &str = "observed_history.jsonl";
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CaptureMetadata {
+    pub timestamp: String,
+    pub url: String,
+    pub status: Option<u32>,
+    pub redirects: Vec<Redirect>,
+    pub ttfb_ms: Option<f64>,
+    pub load_ms: Option<f64>,
+    pub tls_expiry: Option<String>,
+    pub captures: Vec<String>,
+    pub console: Vec<ConsoleMessage>,
+    pub exceptions: Vec<String>,
+    pub failed_requests: Vec<FailedRequest>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl CaptureMetadata {
+    pub fn new(url: &str) -> Self {
+        Self {
+            timestamp: chrono::offset::Local::now().to_rfc3339(),
+            url: url.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Redirect {
+    pub url: String,
+    pub status: u32,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConsoleMessage {
+    pub level: String,
+    pub text: String,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct FailedRequest {
+    pub url: String,
+    pub status: Option<u32>,
+    pub error: Option<String>,
+}
+
+pub fn write_metadata(dir: &Path, metadata: &CaptureMetadata) -> anyhow::Result<()> {
+    let json = serde_json::to_string(metadata)?;
+    fs::write(dir.join(METADATA_FILE), &json)?;
+    let mut history = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(METADATA_HISTORY_FILE))?;
+    writeln!(history, "{}", json)?;
+
+    Ok(())
+}
+
+pub fn read_metadata(dir: &Path) -> Option<CaptureMetadata> {
+    serde_json::from_str(&fs::read_to_string(dir.join(METADATA_FILE)).ok()?).ok()
+}
@@ -1,18 +1,40 @@
+use crate::auth::apply_auth;
+use crate::checks::{run_checks, DocumentRecorder};
+use crate::crawl::{crawl_capture_filename, crawl_site};
+use crate::diagnostics::{new_errors, DiagnosticsRecorder};
+use crate::emulation::{apply_emulation, Emulation};
+use crate::har::{har_filename, HarRecorder};
+use crate::inject::apply_injection;
+use crate::matrix::{matrix_capture_filename, remove_stale_captures, DEFAULT_VIEWPORT, MATRIX_DIR};
+use crate::metadata::{read_metadata, write_metadata, CaptureMetadata};
+use crate::proxy::apply_proxy_auth;
+use crate::series::{extract_values, record_samples};
+use crate::steps::run_steps;
+use crate::target::Target;
 use anyhow::{anyhow, bail};
 use chrono;
+use headless_chrome::browser::default_executable;
 use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
 use headless_chrome::protocol::cdp::Target::CreateTarget;
-use headless_chrome::Browser;
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use std::cell::Cell;
+use std::ffi::OsStr;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
 use std::process::Command;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Once};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{env, fs};
 
+// the additional targets are observed into observed_targets/<name> of the capture directory
+pub const TARGETS_DIR: &str = "observed_targets";
+
 pub fn start_observing(
     target_browser: &str,
-    url: &String,
-    file_format: &String,
+    target: &Target,
+    dir: &Path,
+    file_format: &str,
     interval: u16,
     quiet_flag: bool,
     status_channels: Option<(&mpsc::Sender<String>, &crossbeam_channel::Receiver<()>)>,
@@ -26,140 +48,443 @@ pub fn start_observing(
             bail!("Error: Sending Observer message through mpsc channel");
         }
     } else {
-        let s = if interval == 1 { "" } else { "s" };
-        println!(
-            "{} | Observer started\nURL: {}\nFile Format: {}\nBrowser Capture Interval: {} minute{}",
-            starting_timestamp,
-            url,
+        print_started(target, file_format, interval);
+    }
+
+    observe(interval, status_channels.map(|(_, rx)| rx), || {
+        browser_capture(
+            target_browser,
+            target,
+            dir,
             file_format,
-            interval,
-            s,
-        );
+            quiet_flag,
+            status_channels.map(|(tx, _)| tx),
+        )
+    })?;
+    if let Some((tx, _)) = status_channels {
+        let stopping_timestamp = chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y");
+        if tx
+            .send(format!("Observer stopped | {}", stopping_timestamp))
+            .is_err()
+        {
+            bail!("Error: Sending Observer message through mpsc channel");
+        }
     }
 
+    Ok(())
+}
+
+// observes until a message is received on stop, used when the configuration is reloaded
+pub fn observe_until(
+    target_browser: &str,
+    target: &Target,
+    dir: &Path,
+    file_format: &str,
+    interval: u16,
+    quiet_flag: bool,
+    stop: &crossbeam_channel::Receiver<()>,
+) -> anyhow::Result<()> {
+    print_started(target, file_format, interval);
+    observe(interval, Some(stop), || {
+        browser_capture(target_browser, target, dir, file_format, quiet_flag, None)
+    })?;
+    println!(
+        "{} | Observer stopped\nURL: {}",
+        chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y"),
+        target.url
+    );
+
+    Ok(())
+}
+
+fn print_started(target: &Target, file_format: &str, interval: u16) {
+    let s = if interval == 1 { "" } else { "s" };
+    println!(
+        "{} | Observer started\nURL: {}\nFile Format: {}\nBrowser Capture Interval: {} minute{}",
+        chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y"),
+        target.url,
+        file_format,
+        interval,
+        s,
+    );
+}
+
+fn observe(
+    interval: u16,
+    stop: Option<&crossbeam_channel::Receiver<()>>,
+    capture: impl Fn() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
     let interval = interval as u64 * 60;
-    let status_msg_tx = status_channels.map(|(tx, _)| tx);
     loop {
-        browser_capture(target_browser, url, file_format, quiet_flag, status_msg_tx)?;
-        if let Some((tx, rx)) = status_channels {
-            if rx.recv_timeout(Duration::from_secs(interval)).is_ok() {
-                let stopping_timestamp = chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y");
-                if tx
-                    .send(format!("Observer stopped | {}", stopping_timestamp))
-                    .is_err()
-                {
-                    bail!("Error: Sending Observer message through mpsc channel");
+        capture()?;
+        match stop {
+            Some(rx) => {
+                if rx.recv_timeout(Duration::from_secs(interval)).is_ok() {
+                    return Ok(());
                 }
-
-                return Ok(());
             }
-        } else {
-            sleep(Duration::from_secs(interval));
+            None => sleep(Duration::from_secs(interval)),
         }
     }
 }
 
+// a failed capture is recorded and reported as a warning, only failing to report it
+// stops the observer
 fn browser_capture(
     target_browser: &str,
-    url: &String,
+    target: &Target,
+    dir: &Path,
     file_format: &str,
     quiet_flag: bool,
     status_msg_tx: Option<&mpsc::Sender<String>>,
 ) -> anyhow::Result<()> {
-    let filename = format!("./observed.{}", file_format);
-    match &*target_browser.to_lowercase() {
-        "chromium" | "chrome" => {
-            // catch potential panic from headless_chrome crate as it does not return any error
-            match std::panic::catch_unwind(|| {
-                std::panic::set_hook(Box::new(|_| {
-                    // hook potential panic to avoid printing panic message to terminal
-                }));
-
-                let browser = Browser::default()?;
-                let url_str = url.to_owned();
-                let tab = browser.new_tab_with_options(CreateTarget {
-                    url: url_str,
-                    width: Some(1920),
-                    height: Some(1080),
-                    browser_context_id: None,
-                    enable_begin_frame_control: None,
-                    new_window: None,
-                    background: None,
-                })?;
-
-                if let Err(e) = tab.navigate_to(url) {
-                    bail!("{}\nCapture of {} failed", e, url);
+    let filename = dir
+        .join(format!("observed.{}", file_format))
+        .to_string_lossy()
+        .into_owned();
+    let (values, mut metadata) = match capture_page(
+        target_browser,
+        target,
+        DEFAULT_VIEWPORT,
+        file_format,
+        &filename,
+    ) {
+        Ok(capture) => capture,
+        Err(e) => (Vec::new(), failed_capture(&target.url, e)),
+    };
+
+    if metadata.error.is_none() {
+        let combinations = target
+            .matrix
+            .as_ref()
+            .map(|m| m.combinations(target_browser))
+            .unwrap_or_default();
+        remove_stale_captures(dir, &combinations, file_format)?;
+        if !combinations.is_empty() {
+            fs::create_dir_all(dir.join(MATRIX_DIR))?;
+            for combination in combinations {
+                let matrix_filename = dir
+                    .join(matrix_capture_filename(&combination.name(), file_format))
+                    .to_string_lossy()
+                    .into_owned();
+                match capture_page(
+                    &combination.browser,
+                    &combination.variant(target),
+                    combination.viewport,
+                    file_format,
+                    &matrix_filename,
+                ) {
+                    Ok((_, m)) if m.error.is_none() => metadata.captures.push(matrix_filename),
+                    Ok((_, m)) => metadata.warnings.push(format!(
+                        "{}: {}",
+                        combination.name(),
+                        m.error.unwrap_or_default()
+                    )),
+                    Err(e) => metadata.warnings.push(format!(
+                        "{}: {}",
+                        combination.name(),
+                        error_message(e)
+                    )),
+                }
+            }
+        }
+
+        if let Some(crawl) = &target.crawl {
+            if !["chromium", "chrome"].contains(&&*target_browser.to_lowercase()) {
+                metadata
+                    .warnings
+                    .push("Crawling is only supported with chromium and chrome targets".to_owned());
+            } else {
+                match crawl_site(dir, target, crawl, file_format) {
+                    Ok((pages, warnings)) => {
+                        for page in pages {
+                            let filename = crawl_capture_filename(&page.name, file_format);
+                            metadata
+                                .captures
+                                .push(dir.join(filename).to_string_lossy().into_owned());
+                        }
+                        metadata.warnings.extend(warnings);
+                    }
+                    Err(e) => metadata.warnings.push(error_message(e)),
                 }
-                tab.wait_until_navigated()?;
-
-                let fmt = match &*file_format.to_lowercase() {
-                    "png" => CaptureScreenshotFormatOption::Png,
-                    "jpeg" => CaptureScreenshotFormatOption::Jpeg,
-                    _ => bail!("Error: file format must either be JPEG or PNG"),
-                };
-
-                let image_data = tab.capture_screenshot(fmt, Some(100), None, false)?;
-                fs::write(&filename, image_data)?;
-
-                // unregister panic hook
-                let _ = std::panic::take_hook();
-                Ok(())
-            }) {
-                Ok(ret) => ret?,
-                Err(_) => bail!(
-                    "Error: Capture of {} failed.\nMake sure chrome or chromium is installed",
-                    url
-                ),
             }
         }
+    }
+
+    let previous = read_metadata(dir);
+    for error in new_errors(previous.as_ref(), &metadata) {
+        metadata.warnings.push(format!("New {}", error));
+    }
+
+    record_samples(dir, &metadata.timestamp, &values)?;
+    write_metadata(dir, &metadata)?;
+    for warning in metadata.error.iter().chain(&metadata.warnings) {
+        if let Some(tx) = status_msg_tx {
+            if tx.send(format!("Warning: {}", warning)).is_err() {
+                bail!("Error: Sending Observer message through mpsc channel");
+            }
+        } else {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+
+    if !quiet_flag {
+        let saving_timestamp = chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y");
+        let saved = metadata.error.is_none().then_some(&filename);
+        for filename in metadata.captures.iter().chain(saved) {
+            if let Some(tx) = status_msg_tx {
+                if tx
+                    .send(format!("{} | {}", filename, saving_timestamp))
+                    .is_err()
+                {
+                    bail!("Error: Sending Observer message through mpsc channel");
+                }
+            } else {
+                println!("{} | {} saved", saving_timestamp, filename);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn failed_capture(url: &str, e: anyhow::Error) -> CaptureMetadata {
+    CaptureMetadata {
+        error: Some(error_message(e)),
+        ..CaptureMetadata::new(url)
+    }
+}
+
+// warnings are printed with their own prefix
+fn error_message(e: anyhow::Error) -> String {
+    let message = e.to_string();
+    message
+        .strip_prefix("Error: ")
+        .unwrap_or(&message)
+        .to_owned()
+}
+
+pub fn capture_page(
+    target_browser: &str,
+    target: &Target,
+    viewport: (u32, u32),
+    file_format: &str,
+    filename: &str,
+) -> anyhow::Result<(Vec<(String, f64)>, CaptureMetadata)> {
+    let url = &target.url;
+    Ok(match &*target_browser.to_lowercase() {
+        "chromium" | "chrome" => catch_browser_panic(url, || {
+            let (_browser, tab) = open_tab(target, viewport)?;
+            let mut metadata = CaptureMetadata::new(url);
+            let recorder = DocumentRecorder::attach(&tab)?;
+            let diagnostics = DiagnosticsRecorder::attach(&tab)?;
+            let har = match target.har {
+                true => {
+                    let auth_headers: Vec<String> = target.auth.headers.keys().cloned().collect();
+                    Some(HarRecorder::attach(&tab, &auth_headers)?)
+                }
+                false => None,
+            };
+            let navigation_start = Instant::now();
+            if let Err(e) = tab.navigate_to(url) {
+                bail!("{}\nCapture of {} failed", e, url);
+            }
+            tab.wait_until_navigated()?;
+            metadata.load_ms = Some(navigation_start.elapsed().as_secs_f64() * 1000.0);
+            recorder.fill(&mut metadata);
+            run_checks(&target.checks, &mut metadata)?;
+            if metadata.error.is_some() {
+                diagnostics.fill(&mut metadata);
+                if let Some(har) = har {
+                    har.write(&har_filename(filename), url, metadata.load_ms)?;
+                }
+                return Ok((Vec::new(), metadata));
+            }
+
+            let fmt = screenshot_format(file_format)?;
+            apply_injection(&tab, &target.inject)?;
+            metadata.captures = run_steps(
+                &tab,
+                &target.steps,
+                &target.inject,
+                &fmt,
+                filename,
+                file_format,
+            )?;
+            let values = extract_values(&tab, &target.extractors)?;
+
+            let image_data = tab.capture_screenshot(fmt, Some(100), None, false)?;
+            fs::write(filename, image_data)?;
+            diagnostics.fill(&mut metadata);
+            if let Some(har) = har {
+                har.write(&har_filename(filename), url, metadata.load_ms)?;
+            }
+
+            Ok((values, metadata))
+        })?,
         "edge" => {
             if env::consts::OS != "windows" {
                 bail!(
                     "Error: Edge target only supported on Windows.\nOS detected: {}",
                     env::consts::OS
                 );
+            } else if let Some(feature) = edge_unsupported(target) {
+                bail!(
+                    "Error: {} only supported with chromium and chrome targets",
+                    feature
+                );
             }
 
+            let proxy_args = target
+                .proxy
+                .as_ref()
+                .map(|p| p.browser_args())
+                .unwrap_or_default();
             let screenshot_arg = format!(
-                "--screenshot={}{}",
+                "--screenshot={}",
                 env::current_dir()?
+                    .join(filename)
                     .to_str()
-                    .ok_or_else(|| anyhow!("Error: Reading current directory"))?,
-                filename
+                    .ok_or_else(|| anyhow!("Error: Reading current directory"))?
             );
 
+            let mut args = vec![
+                "--headless".to_owned(),
+                "--disable-gpu".to_owned(),
+                format!("--window-size={},{}", viewport.0, viewport.1),
+            ];
+            if target.emulation.color_scheme.as_deref() == Some("dark") {
+                args.push("--force-dark-mode".to_owned());
+            }
+
             if !Command::new("C:\\Program Files (x86)\\Microsoft\\Edge\\Application\\msedge")
-                .args([
-                    "--headless",
-                    "--disable-gpu",
-                    "--window-size=1920,1080",
-                    &screenshot_arg,
-                    url,
-                ])
+                .args(&args)
+                .args(&proxy_args)
+                .args([&screenshot_arg, url])
                 .status()?
                 .success()
             {
                 bail!("Error: Failed to capture {}", url);
             }
+
+            (Vec::new(), CaptureMetadata::new(url))
         }
         browser => {
             bail!("Error: Unsupported target browser '{}'", browser);
         }
+    })
+}
+
+// what an edge capture cannot apply, edge only takes a headless screenshot
+pub fn edge_unsupported(target: &Target) -> Option<&'static str> {
+    if !target.extractors.is_empty() {
+        Some("Extractors are")
+    } else if target.har {
+        Some("HAR export is")
+    } else if !target.auth.is_empty() {
+        Some("Authentication is")
+    } else if !target.steps.is_empty() {
+        Some("Steps are")
+    } else if !target.inject.is_empty() {
+        Some("Injection is")
+    } else if !(Emulation {
+        color_scheme: None,
+        ..target.emulation.clone()
+    })
+    .is_empty()
+    {
+        Some("Emulation is")
+    } else if target.proxy.as_ref().is_some_and(|p| p.has_credentials()) {
+        Some("Proxy credentials are")
+    } else {
+        None
     }
+}
 
-    if !quiet_flag {
-        let saving_timestamp = chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y");
-        if let Some(tx) = status_msg_tx {
-            if tx
-                .send(format!("{} | {}", filename, saving_timestamp))
-                .is_err()
-            {
-                bail!("Error: Sending Observer message through mpsc channel");
-            }
-        } else {
-            println!("{} | {} saved", saving_timestamp, filename);
-        }
+// launches chrome and opens a blank tab with the target's emulation,
+// authentication and proxy credentials applied, ready for navigation
+pub fn open_tab(target: &Target, viewport: (u32, u32)) -> anyhow::Result<(Browser, Arc<Tab>)> {
+    let proxy_args = target
+        .proxy
+        .as_ref()
+        .map(|p| p.browser_args())
+        .unwrap_or_default();
+    let browser = Browser::new(LaunchOptions {
+        path: Some(
+            default_executable()
+                .map_err(|e| anyhow!("Error: {}\nMake sure chrome or chromium is installed", e))?,
+        ),
+        args: proxy_args.iter().map(OsStr::new).collect(),
+        ..Default::default()
+    })?;
+    let tab = browser.new_tab_with_options(CreateTarget {
+        url: "about:blank".to_owned(),
+        width: Some(viewport.0),
+        height: Some(viewport.1),
+        browser_context_id: None,
+        enable_begin_frame_control: None,
+        new_window: None,
+        background: None,
+    })?;
+
+    apply_emulation(&tab, &target.emulation)?;
+    apply_auth(&tab, &target.auth, &target.url)?;
+    if let Some(proxy) = &target.proxy {
+        apply_proxy_auth(&tab, proxy)?;
     }
 
-    Ok(())
+    Ok((browser, tab))
+}
+
+thread_local! {
+    static QUIET_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+// silences the panic messages of the current thread until dropped, the hook only
+// delegates so that parallel captures cannot restore each other's hook
+struct QuietPanics;
+
+impl QuietPanics {
+    fn new() -> Self {
+        static HOOK: Once = Once::new();
+        HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                if !QUIET_PANICS.with(|q| q.get()) {
+                    previous(info);
+                }
+            }));
+        });
+        QUIET_PANICS.with(|q| q.set(true));
+        QuietPanics
+    }
+}
+
+impl Drop for QuietPanics {
+    fn drop(&mut self) {
+        QUIET_PANICS.with(|q| q.set(false));
+    }
+}
+
+// catch potential panic from headless_chrome crate as it does not return any error
+pub fn catch_browser_panic<T>(
+    url: &str,
+    browse: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let _quiet = QuietPanics::new();
+    match std::panic::catch_unwind(AssertUnwindSafe(browse)) {
+        Ok(ret) => ret,
+        Err(_) => bail!(
+            "Error: Capture of {} failed.\nMake sure chrome or chromium is installed",
+            url
+        ),
+    }
+}
+
+pub fn screenshot_format(file_format: &str) -> anyhow::Result<CaptureScreenshotFormatOption> {
+    Ok(match &*file_format.to_lowercase() {
+        "png" => CaptureScreenshotFormatOption::Png,
+        "jpeg" | "jpg" => CaptureScreenshotFormatOption::Jpeg,
+        _ => bail!("Error: file format must either be JPEG or PNG"),
+    })
 }
@@ -0,0 +1,78 @@
+use crate::auth::expand_env;
+use anyhow::anyhow;
+use headless_chrome::Tab;
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct Proxy {
+    #[schemars(description = "Proxy server (ex: socks5://127.0.0.1:1080)")]
+    pub server: String,
+    #[schemars(description = "Proxy username, can reference an environment variable with ${VAR}")]
+    pub username: Option<String>,
+    #[schemars(description = "Proxy password, can reference an environment variable with ${VAR}")]
+    pub password: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Hosts reached without the proxy")]
+    pub bypass: Vec<String>,
+}
+
+pub fn parse_proxy_server(s: &str) -> Result<String, String> {
+    let server = s.trim().to_lowercase();
+    let address = ["http://", "https://", "socks5://"]
+        .iter()
+        .find_map(|scheme| server.strip_prefix(scheme));
+    match address {
+        Some(a) if !a.is_empty() && !a.contains('@') && !a.contains('/') => Ok(server),
+        _ => Err(
+            "proxy server must follow this format: '[http,https,socks5]://host:port'\n\
+            (credentials are set with proxy username and password)"
+                .to_owned(),
+        ),
+    }
+}
+
+pub fn parse_proxy(p: &Proxy) -> Result<(), (String, String)> {
+    parse_proxy_server(&p.server).map_err(|e| ("server".to_owned(), e))?;
+    if p.username.is_some() != p.password.is_some() {
+        return Err((
+            String::new(),
+            "proxy username and password must be specified together".to_owned(),
+        ));
+    }
+
+    for (key, v) in [("username", &p.username), ("password", &p.password)] {
+        if let Some(v) = v {
+            expand_env(v).map_err(|e| (key.to_owned(), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Proxy {
+    pub fn has_credentials(&self) -> bool {
+        self.username.is_some()
+    }
+
+    // chrome and edge share the same proxy switches
+    pub fn browser_args(&self) -> Vec<String> {
+        let mut args = vec![format!("--proxy-server={}", self.server)];
+        if !self.bypass.is_empty() {
+            args.push(format!("--proxy-bypass-list={}", self.bypass.join(";")));
+        }
+
+        args
+    }
+}
+
+// chrome ignores credentials passed in --proxy-server, the proxy
+// authentication challenge is answered through the Fetch domain instead
+pub fn apply_proxy_auth(tab: &Tab, p: &Proxy) -> anyhow::Result<()> {
+    if let (Some(username), Some(password)) = (&p.username, &p.password) {
+        let username = expand_env(username).map_err(|e| anyhow!("Error: {}", e))?;
+        let password = expand_env(password).map_err(|e| anyhow!("Error: {}", e))?;
+        tab.enable_fetch(None, Some(true))?;
+        tab.authenticate(Some(username), Some(password))?;
+    }
+
+    Ok(())
+}
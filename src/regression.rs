@@ -0,0 +1,356 @@
+use crate::matrix::DEFAULT_VIEWPORT;
+use crate::observer::capture_page;
+use crate::report::{write_html_report, write_junit_report};
+use crate::settings::FILE_FORMATS;
+use crate::target::Target;
+use anyhow::{anyhow, bail};
+use image::{GenericImageView, Rgb, RgbImage};
+use std::fs;
+use std::path::Path;
+
+pub const BASELINE_DIR: &str = "observer_baselines";
+pub const TEST_DIR: &str = "observed_test";
+pub const TEST_REPORT_FILE: &str = "observed_test/report.json";
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Regression {
+    #[schemars(description = "Percentage of pixels allowed to differ from the baseline")]
+    #[schemars(range(min = 0, max = 100))]
+    pub tolerance: f64,
+    #[schemars(description = "Per channel difference under which two pixels are considered equal")]
+    pub pixel_threshold: u8,
+}
+
+impl Default for Regression {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.1,
+            pixel_threshold: 16,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    MissingBaseline,
+    Error,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    pub target: String,
+    pub url: String,
+    pub browser: String,
+    pub viewport: String,
+    pub status: TestStatus,
+    pub diff_percent: Option<f64>,
+    pub baseline: String,
+    pub current: String,
+    pub diff: Option<String>,
+    pub message: Option<String>,
+    pub duration_ms: f64,
+}
+
+pub fn parse_regression(r: &Regression) -> Result<(), (String, String)> {
+    if !(0.0..=100.0).contains(&r.tolerance) {
+        return Err((
+            "tolerance".to_owned(),
+            "regression tolerance must be a percentage within [0, 100]".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn baseline_filename(name: &str, file_format: &str) -> String {
+    format!("{}/{}.{}", BASELINE_DIR, name, file_format)
+}
+
+// a baseline approved in another file format is compared to the capture
+// when there is none in the current one
+fn find_baseline(dir: &Path, name: &str, file_format: &str) -> String {
+    std::iter::once(file_format)
+        .chain(FILE_FORMATS.iter().copied().filter(|f| *f != file_format))
+        .map(|f| baseline_filename(name, f))
+        .find(|b| dir.join(b).exists())
+        .unwrap_or_else(|| baseline_filename(name, file_format))
+}
+
+pub fn test_capture_filename(name: &str, file_format: &str) -> String {
+    format!("{}/{}.{}", TEST_DIR, name, file_format)
+}
+
+fn test_diff_filename(name: &str) -> String {
+    format!("{}/{}_diff.png", TEST_DIR, name)
+}
+
+// captures every target (and matrix combination) once, compares each capture
+// to its baseline and writes the json, junit and html reports, returns the test cases in order,
+// the filenames of the test cases are relative to dir
+pub fn run_test(
+    dir: &Path,
+    target_browser: &str,
+    targets: &[&Target],
+    file_format: &str,
+    quiet_flag: bool,
+) -> anyhow::Result<Vec<TestCase>> {
+    fs::create_dir_all(dir.join(TEST_DIR))?;
+    let timestamp = chrono::offset::Local::now().to_rfc3339();
+    let mut cases = Vec::new();
+    for target in targets {
+        let mut runs = vec![(
+            target.name(),
+            target_browser.to_lowercase(),
+            DEFAULT_VIEWPORT,
+            (*target).clone(),
+        )];
+        if let Some(matrix) = &target.matrix {
+            runs.extend(matrix.combinations(target_browser).iter().map(|c| {
+                (
+                    format!("{}_{}", target.name(), c.name()),
+                    c.browser.clone(),
+                    c.viewport,
+                    c.variant(target),
+                )
+            }));
+        }
+
+        for (name, browser, viewport, variant) in runs {
+            let case = run_case(dir, &name, &browser, viewport, &variant, file_format);
+            if !quiet_flag {
+                let status = match case.status {
+                    TestStatus::Passed => "passed",
+                    TestStatus::Failed => "FAILED",
+                    TestStatus::MissingBaseline => "MISSING BASELINE",
+                    TestStatus::Error => "ERROR",
+                };
+                match &case.message {
+                    Some(m) => println!("{} | {}\n  {}", status, name, m.replace('\n', "\n  ")),
+                    None => println!("{} | {}", status, name),
+                }
+            }
+            cases.push(case);
+        }
+    }
+
+    fs::write(
+        dir.join(TEST_REPORT_FILE),
+        serde_json::to_string_pretty(&cases)?,
+    )?;
+    write_junit_report(dir, &cases, &timestamp)?;
+    write_html_report(dir, &cases, &timestamp)?;
+
+    Ok(cases)
+}
+
+// variants keep the name and url of their target
+fn run_case(
+    dir: &Path,
+    name: &str,
+    browser: &str,
+    viewport: (u32, u32),
+    variant: &Target,
+    file_format: &str,
+) -> TestCase {
+    let start = std::time::Instant::now();
+    let mut case = TestCase {
+        name: name.to_owned(),
+        target: variant.name(),
+        url: variant.url.clone(),
+        browser: browser.to_owned(),
+        viewport: format!("{}x{}", viewport.0, viewport.1),
+        status: TestStatus::Error,
+        diff_percent: None,
+        baseline: find_baseline(dir, name, file_format),
+        current: test_capture_filename(name, file_format),
+        diff: None,
+        message: None,
+        duration_ms: 0.0,
+    };
+
+    // a failed check is an error of the case
+    let current = dir.join(&case.current).to_string_lossy().into_owned();
+    let captured = capture_page(browser, variant, viewport, file_format, &current)
+        .and_then(|(_, metadata)| metadata.error.map_or(Ok(()), |e| Err(anyhow!(e))));
+    if let Err(e) = captured {
+        case.message = Some(e.to_string());
+    } else if !dir.join(&case.baseline).exists() {
+        case.status = TestStatus::MissingBaseline;
+        case.message = Some(format!(
+            "No baseline found at {}\nApprove the current capture to create it",
+            case.baseline
+        ));
+    } else {
+        let diff = test_diff_filename(name);
+        match compare_images(
+            &dir.join(&case.baseline).to_string_lossy(),
+            &current,
+            variant.regression.pixel_threshold,
+            &dir.join(&diff).to_string_lossy(),
+        ) {
+            Ok(percent) => {
+                case.diff_percent = Some(percent);
+                case.diff = Some(diff);
+                case.status = diff_status(percent, variant.regression.tolerance);
+                if case.status == TestStatus::Failed {
+                    case.message = Some(format!(
+                        "{:.3}% of pixels differ from the baseline (tolerance: {}%)",
+                        percent, variant.regression.tolerance
+                    ));
+                }
+            }
+            Err(e) => case.message = Some(e.to_string()),
+        }
+    }
+
+    case.duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+    case
+}
+
+// writes a diff image highlighting differing pixels in red over a faded
+// current capture and returns the percentage of differing pixels
+fn diff_status(percent: f64, tolerance: f64) -> TestStatus {
+    match percent <= tolerance {
+        true => TestStatus::Passed,
+        false => TestStatus::Failed,
+    }
+}
+
+pub fn compare_images(
+    baseline: &str,
+    current: &str,
+    pixel_threshold: u8,
+    diff_filename: &str,
+) -> anyhow::Result<f64> {
+    let baseline = image::open(baseline)?;
+    let current = image::open(current)?;
+    if baseline.dimensions() != current.dimensions() {
+        let (bw, bh) = baseline.dimensions();
+        let (cw, ch) = current.dimensions();
+        bail!(
+            "Error: Capture size {}x{} differs from baseline size {}x{}",
+            cw,
+            ch,
+            bw,
+            bh
+        );
+    }
+
+    let (baseline, current) = (baseline.to_rgb8(), current.to_rgb8());
+    let mut diff = RgbImage::new(current.width(), current.height());
+    let mut differing = 0u64;
+    for (x, y, pixel) in current.enumerate_pixels() {
+        let other = baseline.get_pixel(x, y);
+        let differs = pixel
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .any(|(a, b)| a.abs_diff(*b) > pixel_threshold);
+        if differs {
+            differing += 1;
+            diff.put_pixel(x, y, Rgb([255, 0, 0]));
+        } else {
+            diff.put_pixel(x, y, Rgb(pixel.0.map(|c| 191 + c / 4)));
+        }
+    }
+    diff.save(diff_filename)?;
+
+    let total = current.width() as u64 * current.height() as u64;
+    Ok(match total {
+        0 => 0.0,
+        _ => differing as f64 * 100.0 / total as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(std::path::PathBuf);
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn temp_dir(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("observer_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    // a gray square image with pixel (1, 1) set to corner
+    fn image(dir: &Path, name: &str, size: u32, corner: [u8; 3]) -> String {
+        let mut image = RgbImage::from_pixel(size, size, Rgb([100, 100, 100]));
+        image.put_pixel(1, 1, Rgb(corner));
+        let path = dir.join(name).to_string_lossy().into_owned();
+        image.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn compare() {
+        let dir = temp_dir("compare");
+        let diff = dir.0.join("diff.png").to_string_lossy().into_owned();
+        let baseline = image(&dir.0, "baseline.png", 2, [100, 100, 100]);
+        let same = image(&dir.0, "same.png", 2, [100, 100, 100]);
+        let close = image(&dir.0, "close.png", 2, [110, 90, 100]);
+        let different = image(&dir.0, "different.png", 2, [200, 100, 100]);
+        let larger = image(&dir.0, "larger.png", 3, [100, 100, 100]);
+
+        assert_eq!(compare_images(&baseline, &same, 16, &diff).unwrap(), 0.0);
+        // within the pixel threshold
+        assert_eq!(compare_images(&baseline, &close, 16, &diff).unwrap(), 0.0);
+        assert_eq!(compare_images(&baseline, &close, 5, &diff).unwrap(), 25.0);
+        assert_eq!(
+            compare_images(&baseline, &different, 16, &diff).unwrap(),
+            25.0
+        );
+        assert_eq!(
+            image::open(&diff).unwrap().to_rgb8().get_pixel(1, 1),
+            &Rgb([255, 0, 0])
+        );
+
+        let error = compare_images(&baseline, &larger, 16, &diff).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Capture size 3x3 differs from baseline size 2x2"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn tolerance() {
+        assert!(diff_status(0.0, 0.0) == TestStatus::Passed);
+        assert!(diff_status(0.1, 0.1) == TestStatus::Passed);
+        assert!(diff_status(0.11, 0.1) == TestStatus::Failed);
+        assert!(diff_status(25.0, 100.0) == TestStatus::Passed);
+    }
+
+    #[test]
+    fn baseline_in_another_format() {
+        let dir = temp_dir("baselines");
+        fs::create_dir_all(dir.0.join(BASELINE_DIR)).unwrap();
+        assert_eq!(
+            find_baseline(&dir.0, "home", "jpeg"),
+            "observer_baselines/home.jpeg"
+        );
+        image(&dir.0, "observer_baselines/home.png", 2, [0, 0, 0]);
+        assert_eq!(
+            find_baseline(&dir.0, "home", "jpeg"),
+            "observer_baselines/home.png"
+        );
+        image(&dir.0, "observer_baselines/home.jpeg", 2, [0, 0, 0]);
+        assert_eq!(
+            find_baseline(&dir.0, "home", "jpeg"),
+            "observer_baselines/home.jpeg"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::observer::{observe_until, TARGETS_DIR};
+use crate::settings::Settings;
+use crate::target::Target;
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// editors write a file in several steps, events closer than this are one reload
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// a reload is requested when the configuration file changes or on SIGHUP
+pub fn watch_config(file: Option<&Path>) -> mpsc::Receiver<()> {
+    let (tx, rx) = mpsc::channel();
+
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::SIGHUP;
+        use signal_hook::iterator::Signals;
+
+        let tx = tx.clone();
+        match Signals::new([SIGHUP]) {
+            Ok(mut signals) => {
+                thread::spawn(move || {
+                    for _ in signals.forever() {
+                        if tx.send(()).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            Err(e) => eprintln!("Warning: SIGHUP will not reload the configuration: {}", e),
+        }
+    }
+
+    if let Some(file) = file {
+        let file = file.to_owned();
+        thread::spawn(move || {
+            if let Err(e) = watch_file(&file, &tx) {
+                eprintln!(
+                    "Warning: {} is not watched for changes: {}",
+                    file.display(),
+                    e
+                );
+            }
+        });
+    }
+
+    rx
+}
+
+// the directory is watched as editors often replace the file instead of writing to it
+#[cfg(target_os = "linux")]
+fn watch_file(file: &Path, tx: &mpsc::Sender<()>) -> anyhow::Result<()> {
+    use inotify::{Inotify, WatchMask};
+
+    let dir = match file.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let mut inotify = Inotify::init()?;
+    inotify.watches().add(
+        dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+    )?;
+
+    let mut buffer = [0; 4096];
+    loop {
+        let events = inotify.read_events_blocking(&mut buffer)?;
+        if events.into_iter().any(|e| e.name == file.file_name()) {
+            tx.send(())?;
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_file(file: &Path, tx: &mpsc::Sender<()>) -> anyhow::Result<()> {
+    let modified = || std::fs::metadata(file).and_then(|m| m.modified()).ok();
+    let mut last = modified();
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let current = modified();
+        if current != last {
+            last = current;
+            tx.send(())?;
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct ObserverSpec {
+    target_browser: String,
+    target: Target,
+    dir: PathBuf,
+    file_format: String,
+    interval: u16,
+}
+
+struct RunningObserver {
+    spec: ObserverSpec,
+    stop: crossbeam_channel::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+// one observer thread per observed target, keyed by target name
+struct Supervisor {
+    observers: BTreeMap<String, RunningObserver>,
+    // like the server, the observers keep the capture directory they started with
+    capture_dir: PathBuf,
+    quiet_flag: bool,
+    // without a configuration file nothing can fix a failing target
+    exit_on_error: bool,
+}
+
+impl Supervisor {
+    fn new(capture_dir: &Path, quiet_flag: bool, exit_on_error: bool) -> Self {
+        Self {
+            observers: BTreeMap::new(),
+            capture_dir: capture_dir.to_owned(),
+            quiet_flag,
+            exit_on_error,
+        }
+    }
+
+    // removed targets stop, changed ones restart and new ones start
+    fn apply(&mut self, s: &Settings) {
+        let mut specs: BTreeMap<String, ObserverSpec> = observed_targets(s, &self.capture_dir)
+            .into_iter()
+            .map(|(t, dir)| {
+                let spec = ObserverSpec {
+                    target_browser: s.target_browser.clone(),
+                    target: t.clone(),
+                    dir,
+                    file_format: s.file_format.clone(),
+                    interval: s.interval,
+                };
+                (t.name(), spec)
+            })
+            .collect();
+
+        let names: Vec<String> = self.observers.keys().cloned().collect();
+        for name in names {
+            if specs.get(&name) == self.observers.get(&name).map(|o| &o.spec) {
+                specs.remove(&name);
+            } else if let Some(observer) = self.observers.remove(&name) {
+                // the observer stops after its current capture
+                let _ = observer.stop.send(());
+                let _ = observer.handle.join();
+            }
+        }
+
+        for (name, spec) in specs {
+            let observer = self.start(spec);
+            self.observers.insert(name, observer);
+        }
+    }
+
+    fn start(&self, spec: ObserverSpec) -> RunningObserver {
+        let (stop, stop_rx) = crossbeam_channel::bounded(1);
+        let (quiet_flag, exit_on_error) = (self.quiet_flag, self.exit_on_error);
+        let s = spec.clone();
+        let handle = thread::spawn(move || {
+            let observed = fs::create_dir_all(&s.dir)
+                .map_err(|e| anyhow!("Error: {}: {}", s.dir.display(), e))
+                .and_then(|_| {
+                    observe_until(
+                        &s.target_browser,
+                        &s.target,
+                        &s.dir,
+                        &s.file_format,
+                        s.interval,
+                        quiet_flag,
+                        &stop_rx,
+                    )
+                });
+            if let Err(e) = observed {
+                eprintln!("{e}");
+                if exit_on_error {
+                    std::process::exit(1);
+                }
+                eprintln!("{} stopped until the configuration changes", s.target.url);
+            }
+        });
+
+        RunningObserver { spec, stop, handle }
+    }
+}
+
+// the top-level target is captured into the capture directory, served by the preview,
+// the ones listed under targets into their own directory of TARGETS_DIR
+pub fn observed_targets<'a>(s: &'a Settings, capture_dir: &Path) -> Vec<(&'a Target, PathBuf)> {
+    s.all_targets()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, t)| !t.url.is_empty())
+        .map(|(i, t)| match i {
+            0 => (t, capture_dir.to_owned()),
+            _ => (t, capture_dir.join(TARGETS_DIR).join(t.name())),
+        })
+        .collect()
+}
+
+// applies every reload of the configuration to the observers (when observing)
+// and sends the file format and interval to the server (when serving), an
+// invalid configuration is reported and the previous one is kept
+pub fn supervise(
+    s: Settings,
+    observing: bool,
+    server: Option<crossbeam_channel::Sender<(String, u16)>>,
+) -> anyhow::Result<()> {
+    let file: Option<PathBuf> = s.config_file().map(|f| f.to_owned());
+    let mut supervisor = Supervisor::new(s.capture_path(), s.quiet_flag, file.is_none());
+    if observing {
+        supervisor.apply(&s);
+    }
+
+    let reloads = watch_config(file.as_deref());
+    let mut current = s;
+    while reloads.recv().is_ok() {
+        while reloads.recv_timeout(RELOAD_DEBOUNCE).is_ok() {}
+
+        let s = match current.reload() {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Configuration not reloaded: {e}");
+                continue;
+            }
+        };
+        if !s.quiet_flag {
+            println!(
+                "{} | Configuration reloaded",
+                chrono::offset::Local::now().format("%H:%M:%S-%d/%m/%Y")
+            );
+        }
+        if (
+            &s.host,
+            s.port,
+            &s.server_ip,
+            &s.capture_dir,
+            &s.tls,
+            &s.approve_token,
+        ) != (
+            &current.host,
+            current.port,
+            &current.server_ip,
+            &current.capture_dir,
+            &current.tls,
+            &current.approve_token,
+        ) {
+            eprintln!(
+                "Warning: host, port, server_ip, capture_dir, tls and approve_token changes are \
+                applied on restart"
+            );
+        }
+
+        if observing {
+            supervisor.apply(&s);
+        }
+        if let Some(server) = &server {
+            if (&s.file_format, s.interval) != (&current.file_format, current.interval) {
+                server.send((s.file_format.clone(), s.interval))?;
+            }
+        }
+        current = s;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::regression::{TestCase, TestStatus};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+
+pub const JUNIT_REPORT_FILE: &str = "observed_test/junit.xml";
+pub const HTML_REPORT_FILE: &str = "observed_test/report.html";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// one testcase per target and matrix combination, missing baselines count as failures
+pub fn write_junit_report(dir: &Path, cases: &[TestCase], timestamp: &str) -> anyhow::Result<()> {
+    let count = |status: TestStatus| cases.iter().filter(|c| c.status == status).count();
+    let failures = count(TestStatus::Failed) + count(TestStatus::MissingBaseline);
+    let time = cases.iter().map(|c| c.duration_ms).sum::<f64>() / 1000.0;
+
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_owned();
+    xml += &format!(
+        "<testsuites tests=\"{0}\" failures=\"{1}\" errors=\"{2}\" time=\"{3:.3}\">\n\
+        \x20 <testsuite name=\"observer\" tests=\"{0}\" failures=\"{1}\" errors=\"{2}\" \
+        time=\"{3:.3}\" timestamp=\"{4}\">\n",
+        cases.len(),
+        failures,
+        count(TestStatus::Error),
+        time,
+        escape(timestamp),
+    );
+    for case in cases {
+        xml += &format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n",
+            escape(&case.target),
+            escape(&format!("{} {} {}", case.name, case.browser, case.viewport)),
+            case.duration_ms / 1000.0
+        );
+        let message = escape(case.message.as_deref().unwrap_or_default());
+        match case.status {
+            TestStatus::Passed => (),
+            TestStatus::Failed | TestStatus::MissingBaseline => {
+                xml += &format!("      <failure message=\"{0}\">{0}</failure>\n", message);
+            }
+            TestStatus::Error => {
+                xml += &format!("      <error message=\"{0}\">{0}</error>\n", message);
+            }
+        }
+        xml += &format!(
+            "      <system-out>url: {}\nbaseline: {}\ncurrent: {}</system-out>\n    </testcase>\n",
+            escape(&case.url),
+            escape(&case.baseline),
+            escape(&case.current)
+        );
+    }
+    xml += "  </testsuite>\n</testsuites>\n";
+
+    fs::write(dir.join(JUNIT_REPORT_FILE), xml)?;
+
+    Ok(())
+}
+
+// images are embedded as data urls so the report can be opened on its own
+pub fn write_html_report(dir: &Path, cases: &[TestCase], timestamp: &str) -> anyhow::Result<()> {
+    let mut rows = String::new();
+    for case in cases {
+        let status = match case.status {
+            TestStatus::Passed => "passed",
+            TestStatus::Failed => "failed",
+            TestStatus::MissingBaseline => "missing baseline",
+            TestStatus::Error => "error",
+        };
+        let diff_percent = case
+            .diff_percent
+            .map(|p| format!(" ({:.3}%)", p))
+            .unwrap_or_default();
+        rows += &format!(
+            "    <tr class=\"{}\"><td>{}<br>{}<br>{} {}</td><td>{}{}<br>{}</td>{}{}{}</tr>\n",
+            if case.status == TestStatus::Passed {
+                "passed"
+            } else {
+                "failed"
+            },
+            escape(&case.name),
+            escape(&case.url),
+            escape(&case.browser),
+            escape(&case.viewport),
+            status,
+            diff_percent,
+            escape(case.message.as_deref().unwrap_or_default()),
+            embedded_image(dir, &case.baseline),
+            embedded_image(dir, &case.current),
+            case.diff
+                .as_deref()
+                .map(|diff| embedded_image(dir, diff))
+                .unwrap_or("<td></td>".to_owned()),
+        );
+    }
+
+    let html = include_str!("report_template.html")
+        .replacen('#', &escape(timestamp), 1)
+        .replacen('#', &rows, 1);
+    fs::write(dir.join(HTML_REPORT_FILE), html)?;
+
+    Ok(())
+}
+
+fn embedded_image(dir: &Path, filename: &str) -> String {
+    let mime = match filename.rsplit('.').next() {
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+    match fs::read(dir.join(filename)) {
+        Ok(data) => format!(
+            "<td><img src=\"data:{};base64,{}\" alt=\"{}\"></td>",
+            mime,
+            STANDARD.encode(data),
+            escape(filename)
+        ),
+        Err(_) => "<td></td>".to_owned(),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::emulation::COLOR_SCHEMES;
+use crate::settings::{Settings, FILE_FORMATS, TARGET_BROWSERS};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ArrayValidation, InstanceType, Schema, SchemaObject};
+use serde_json::json;
+
+// JSON Schema of the configuration file for editors and YAML language servers,
+// also used by config check
+pub fn config_schema() -> anyhow::Result<serde_json::Value> {
+    let mut schema = schemars::schema_for!(Settings);
+    schema.schema.metadata().title = Some("observer configuration".to_owned());
+    // every top-level key has a default layer, url is only required by some commands
+    schema.schema.object().required.clear();
+    Ok(serde_json::to_value(schema)?)
+}
+
+fn one_of(values: &[&str]) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(values.iter().map(|v| json!(v)).collect()),
+        ..Default::default()
+    }
+}
+
+fn array_of(items: SchemaObject) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(Schema::Object(items).into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+// the values accepted by the parse_* validators, which also accept them in any case
+// while the schema only lists the lowercase ones (JSON Schema enums are case sensitive)
+pub fn file_format(_: &mut SchemaGenerator) -> Schema {
+    one_of(&FILE_FORMATS).into()
+}
+
+pub fn target_browser(_: &mut SchemaGenerator) -> Schema {
+    one_of(&TARGET_BROWSERS).into()
+}
+
+pub fn color_scheme(_: &mut SchemaGenerator) -> Schema {
+    let mut schema = one_of(&COLOR_SCHEMES);
+    schema.instance_type = Some(vec![InstanceType::String, InstanceType::Null].into());
+    schema
+        .enum_values
+        .get_or_insert_with(Vec::new)
+        .push(json!(null));
+    schema.into()
+}
+
+pub fn browsers(_: &mut SchemaGenerator) -> Schema {
+    array_of(one_of(&TARGET_BROWSERS))
+}
+
+pub fn color_schemes(_: &mut SchemaGenerator) -> Schema {
+    array_of(one_of(&COLOR_SCHEMES))
+}
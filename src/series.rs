@@ -0,0 +1,216 @@
+use anyhow::{anyhow, bail};
+use headless_chrome::Tab;
+use regex::Regex;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub const SERIES_FILE: &str = "observed_series.csv";
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+pub struct Extractor {
+    #[schemars(description = "Name of the series")]
+    pub name: String,
+    #[schemars(description = "CSS selector of the element holding the value")]
+    pub selector: Option<String>,
+    #[schemars(
+        description = "Regular expression matched on the page source, the first capture group is the value"
+    )]
+    pub regex: Option<String>,
+    #[serde(default = "default_decimal_separator")]
+    #[schemars(description = "Decimal separator of the value")]
+    pub decimal_separator: char,
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+#[derive(serde::Serialize)]
+pub struct Sample {
+    pub timestamp: String,
+    pub name: String,
+    pub value: f64,
+}
+
+pub fn parse_extractors(extractors: &[Extractor]) -> Result<(), (String, String)> {
+    for (i, e) in extractors.iter().enumerate() {
+        if e.name.is_empty()
+            || !e
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err((
+                format!("[{}].name", i),
+                format!(
+                    "extractor name '{}' must only contain letters, digits, '_' and '-'",
+                    e.name
+                ),
+            ));
+        }
+
+        match (&e.selector, &e.regex) {
+            (Some(_), None) => (),
+            (None, Some(r)) => {
+                if let Err(err) = Regex::new(r) {
+                    return Err((
+                        format!("[{}].regex", i),
+                        format!("extractor '{}' has an invalid regex\n{}", e.name, err),
+                    ));
+                }
+            }
+            _ => {
+                return Err((
+                    format!("[{}]", i),
+                    format!(
+                        "extractor '{}' must specify either a selector or a regex",
+                        e.name
+                    ),
+                ))
+            }
+        }
+
+        if ![',', '.'].contains(&e.decimal_separator) {
+            return Err((
+                format!("[{}].decimal_separator", i),
+                format!(
+                    "extractor '{}' decimal separator must either be ',' or '.'",
+                    e.name
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn extract_values(tab: &Tab, extractors: &[Extractor]) -> anyhow::Result<Vec<(String, f64)>> {
+    let mut values = Vec::with_capacity(extractors.len());
+    let mut content = None;
+    for e in extractors {
+        let text = if let Some(selector) = &e.selector {
+            tab.find_element(selector)
+                .and_then(|el| el.get_inner_text())
+                .map_err(|err| anyhow!("Error: Extractor '{}' failed\n{}", e.name, err))?
+        } else if let Some(r) = &e.regex {
+            if content.is_none() {
+                content = Some(tab.get_content()?);
+            }
+
+            let re = Regex::new(r)?;
+            let captures = re
+                .captures(content.as_deref().unwrap_or_default())
+                .ok_or_else(|| anyhow!("Error: Extractor '{}' regex did not match", e.name))?;
+            captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map(|m| m.as_str().to_owned())
+                .unwrap_or_default()
+        } else {
+            bail!("Error: Extractor '{}' has no selector nor regex", e.name);
+        };
+
+        match parse_number(&text, e.decimal_separator) {
+            Some(v) => values.push((e.name.clone(), v)),
+            None => bail!(
+                "Error: Extractor '{}' could not parse a number from '{}'",
+                e.name,
+                text.trim()
+            ),
+        }
+    }
+
+    Ok(values)
+}
+
+// keep digits, sign and decimal separator so that values such as "$1,299.00"
+// or "1 299,00 €" both parse (grouping characters are dropped)
+pub fn parse_number(text: &str, decimal_separator: char) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let negative = text[..start].trim_end().ends_with('-');
+    let mut number = String::new();
+    if negative {
+        number.push('-');
+    }
+
+    for c in text[start..].chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else if c == decimal_separator {
+            number.push('.');
+        } else if c.is_whitespace() || c == ',' || c == '.' || c == '\'' || c == '\u{a0}' {
+            // grouping character
+        } else {
+            break;
+        }
+    }
+
+    number.parse().ok()
+}
+
+pub fn record_samples(dir: &Path, timestamp: &str, values: &[(String, f64)]) -> anyhow::Result<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(SERIES_FILE))?;
+    for (name, value) in values {
+        writeln!(file, "{},{},{}", timestamp, name, value)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_samples(dir: &Path) -> Vec<Sample> {
+    let contents = match fs::read_to_string(dir.join(SERIES_FILE)) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ',');
+            Some(Sample {
+                timestamp: fields.next()?.to_owned(),
+                name: fields.next()?.to_owned(),
+                value: fields.next()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        for (text, decimal_separator, expected) in [
+            ("42", '.', Some(42.0)),
+            ("$1,299.00", '.', Some(1299.0)),
+            ("1 299,50 €", ',', Some(1299.5)),
+            ("1.299,50", ',', Some(1299.5)),
+            ("1'000'000", '.', Some(1_000_000.0)),
+            ("1\u{a0}000", '.', Some(1000.0)),
+            ("In stock: 12 units", '.', Some(12.0)),
+            ("-3.5", '.', Some(-3.5)),
+            ("Balance: - 7", '.', Some(-7.0)),
+            ("out of stock", '.', None),
+            ("", '.', None),
+            ("1.2.3", '.', None),
+        ] {
+            assert_eq!(
+                parse_number(text, decimal_separator),
+                expected,
+                "{:?} with {:?}",
+                text,
+                decimal_separator
+            );
+        }
+    }
+}
@@ -1,4 +1,6 @@
+mod redirect;
 mod routes;
 mod startup;
+mod watcher;
 
-pub use startup::start_server;
+pub use startup::{server_address, start_server, ServerOptions};
@@ -0,0 +1,62 @@
+use actix_web::http::header::LOCATION;
+use actix_web::web::Data;
+use actix_web::{HttpRequest, HttpResponse};
+
+pub struct HttpsPort(pub u16);
+
+// temporary so that browsers do not keep redirecting once tls is disabled
+pub async fn redirect_to_https(req: HttpRequest, https_port: Data<HttpsPort>) -> HttpResponse {
+    let host = req.connection_info().host().to_owned();
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+
+    HttpResponse::TemporaryRedirect()
+        .insert_header((LOCATION, https_location(&host, path, https_port.0)))
+        .finish()
+}
+
+// the requested name without the http port, IPv6 addresses keep their brackets
+fn https_location(host: &str, path: &str, https_port: u16) -> String {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !host.ends_with(']') && port.bytes().all(|b| b.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    };
+    match https_port {
+        443 => format!("https://{}{}", name, path),
+        port => format!("https://{}:{}{}", name, port, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations() {
+        for (host, port, expected) in [
+            ("example.com", 443, "https://example.com/preview?a=1"),
+            ("example.com:8080", 443, "https://example.com/preview?a=1"),
+            (
+                "example.com:8080",
+                8443,
+                "https://example.com:8443/preview?a=1",
+            ),
+            (
+                "192.168.1.70:80",
+                1337,
+                "https://192.168.1.70:1337/preview?a=1",
+            ),
+            ("[::1]", 443, "https://[::1]/preview?a=1"),
+            ("[::1]:8080", 443, "https://[::1]/preview?a=1"),
+            ("[fe80::1]:8080", 8443, "https://[fe80::1]:8443/preview?a=1"),
+        ] {
+            assert_eq!(
+                https_location(host, "/preview?a=1", port),
+                expected,
+                "{}",
+                host
+            );
+        }
+    }
+}
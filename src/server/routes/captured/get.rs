@@ -0,0 +1,19 @@
+use crate::server::startup::PreviewHtmlContents;
+use crate::server::watcher::CaptureWatcher;
+use actix_web::web::Data;
+use actix_web::{get, HttpResponse};
+
+// the preview version lets open preview pages notice a configuration reload
+#[get("/captured")]
+pub async fn captured(
+    watcher: Data<CaptureWatcher>,
+    preview_html: Data<PreviewHtmlContents>,
+) -> HttpResponse {
+    let version = ("X-Preview-Version", preview_html.version().to_string());
+    match watcher.last_capture() {
+        Some(t) => HttpResponse::Ok().insert_header(version).body(t),
+        None => HttpResponse::NotFound()
+            .insert_header(version)
+            .body("no capture found"),
+    }
+}
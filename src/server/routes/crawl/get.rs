@@ -0,0 +1,47 @@
+use crate::crawl::{crawl_capture_filename, read_crawl_index};
+use crate::server::routes::escape;
+use crate::server::startup::ObservedFileName;
+use actix_files::NamedFile;
+use actix_web::error::ErrorNotFound;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Result};
+
+#[get("/crawl")]
+pub async fn crawl(observed_file: Data<ObservedFileName>) -> HttpResponse {
+    let mut table =
+        "    <tr><th>Depth</th><th>Page</th><th>Captured</th><th></th></tr>\n".to_owned();
+    for page in read_crawl_index(observed_file.dir()) {
+        let url = escape(&page.url);
+        table += &format!(
+            "    <tr><td>{}</td><td><a href=\"{1}\">{1}</a></td><td>{2}</td>\
+            <td><a href=\"crawl/{3}\"><img src=\"crawl/{3}\" alt=\"{3}\"></a></td></tr>\n",
+            page.depth, url, page.timestamp, page.name
+        );
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(include_str!("../../crawl_template.html").replacen('#', &table, 1))
+}
+
+#[get("/crawl/{name}")]
+pub async fn crawl_capture(
+    name: Path<String>,
+    observed_file: Data<ObservedFileName>,
+) -> Result<NamedFile> {
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(ErrorNotFound("capture not found"));
+    }
+
+    let file_format = observed_file.file_format();
+    Ok(NamedFile::open_async(
+        observed_file
+            .dir()
+            .join(crawl_capture_filename(&name, &file_format)),
+    )
+    .await?)
+}
@@ -0,0 +1,2 @@
+mod get;
+pub use get::{crawl, crawl_capture};
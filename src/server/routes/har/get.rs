@@ -0,0 +1,16 @@
+use crate::har::HAR_FILE;
+use crate::server::startup::ObservedFileName;
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Data;
+use actix_web::{get, Result};
+
+#[get("/har")]
+pub async fn har(observed_file: Data<ObservedFileName>) -> Result<NamedFile> {
+    Ok(NamedFile::open_async(observed_file.dir().join(HAR_FILE))
+        .await?
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("observed.har".to_owned())],
+        }))
+}
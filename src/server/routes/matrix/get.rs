@@ -0,0 +1,86 @@
+use crate::matrix::{matrix_capture_filename, MATRIX_DIR};
+use crate::server::startup::ObservedFileName;
+use actix_files::NamedFile;
+use actix_web::error::ErrorNotFound;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Result};
+use std::collections::BTreeSet;
+use std::fs;
+
+#[get("/matrix")]
+pub async fn matrix(observed_file: Data<ObservedFileName>) -> HttpResponse {
+    let extension = format!(".{}", observed_file.file_format());
+    let names = fs::read_dir(observed_file.dir().join(MATRIX_DIR))
+        .map(|dir| {
+            dir.filter_map(|e| e.ok()?.file_name().into_string().ok())
+                .filter_map(|f| f.strip_suffix(&extension).map(str::to_owned))
+                .collect::<BTreeSet<_>>()
+        })
+        .unwrap_or_default();
+
+    // names are browser_WIDTHxHEIGHT[_scheme]: one row per viewport,
+    // one column per browser and color scheme
+    let mut rows = BTreeSet::new();
+    let mut columns = BTreeSet::new();
+    for name in &names {
+        let mut parts = name.splitn(3, '_');
+        let browser = parts.next().unwrap_or_default();
+        let viewport = parts.next().unwrap_or_default();
+        let column = match parts.next() {
+            Some(scheme) => format!("{}_{}", browser, scheme),
+            None => browser.to_owned(),
+        };
+        rows.insert(viewport.to_owned());
+        columns.insert(column);
+    }
+
+    let mut table = "    <tr><th></th>".to_owned();
+    for column in &columns {
+        table += &format!("<th>{}</th>", column.replace('_', " "));
+    }
+    table += "</tr>\n";
+    for row in &rows {
+        table += &format!("    <tr><th>{}</th>", row);
+        for column in &columns {
+            let (browser, scheme) = match column.split_once('_') {
+                Some((b, s)) => (b, format!("_{}", s)),
+                None => (column.as_str(), String::new()),
+            };
+            let name = format!("{}_{}{}", browser, row, scheme);
+            if names.contains(&name) {
+                table += &format!(
+                    "<td><a href=\"matrix/{0}\"><img src=\"matrix/{0}\" alt=\"{0}\"></a></td>",
+                    name
+                );
+            } else {
+                table += "<td></td>";
+            }
+        }
+        table += "</tr>\n";
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(include_str!("../../matrix_template.html").replacen('#', &table, 1))
+}
+
+#[get("/matrix/{name}")]
+pub async fn matrix_capture(
+    name: Path<String>,
+    observed_file: Data<ObservedFileName>,
+) -> Result<NamedFile> {
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(ErrorNotFound("capture not found"));
+    }
+
+    Ok(NamedFile::open_async(
+        observed_file
+            .dir()
+            .join(matrix_capture_filename(&name, &observed_file.file_format())),
+    )
+    .await?)
+}
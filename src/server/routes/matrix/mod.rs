@@ -0,0 +1,2 @@
+mod get;
+pub use get::{matrix, matrix_capture};
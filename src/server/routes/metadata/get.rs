@@ -0,0 +1,12 @@
+use crate::metadata::read_metadata;
+use crate::server::startup::ObservedFileName;
+use actix_web::web::Data;
+use actix_web::{get, HttpResponse};
+
+#[get("/metadata")]
+pub async fn metadata(observed_file: Data<ObservedFileName>) -> HttpResponse {
+    match read_metadata(observed_file.dir()) {
+        Some(m) => HttpResponse::Ok().json(m),
+        None => HttpResponse::NotFound().body("no capture metadata found"),
+    }
+}
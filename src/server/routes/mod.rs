@@ -1,5 +1,26 @@
+mod captured;
+mod crawl;
+mod har;
+mod matrix;
+mod metadata;
 mod preview;
+mod regression;
+mod series;
 mod update;
 
+pub use captured::*;
+pub use crawl::*;
+pub use har::*;
+pub use matrix::*;
+pub use metadata::*;
 pub use preview::*;
+pub use regression::*;
+pub use series::*;
 pub use update::*;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -7,5 +7,5 @@ use actix_web::{get, HttpResponse};
 pub async fn preview(preview_html: Data<PreviewHtmlContents>) -> HttpResponse {
     HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(preview_html.0.clone())
+        .body(preview_html.html())
 }
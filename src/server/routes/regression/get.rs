@@ -0,0 +1,141 @@
+use crate::baseline::{read_history, read_test_report};
+use crate::regression::TestStatus;
+use crate::server::routes::escape;
+use crate::server::startup::{ApproveToken, ObservedFileName};
+use actix_files::NamedFile;
+use actix_web::error::ErrorNotFound;
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Path};
+use actix_web::{get, HttpResponse, Result};
+
+#[get("/regression")]
+pub async fn regression(
+    approve_token: Data<ApproveToken>,
+    observed_file: Data<ObservedFileName>,
+) -> HttpResponse {
+    let mut table = "    <tr><th>Test case</th><th>Status</th><th>Baseline</th><th>Current</th>\
+        <th>Diff</th><th></th></tr>\n"
+        .to_owned();
+    for case in read_test_report(observed_file.dir()) {
+        let status = match case.status {
+            TestStatus::Passed => "passed".to_owned(),
+            TestStatus::Failed => format!("failed ({:.3}%)", case.diff_percent.unwrap_or_default()),
+            TestStatus::MissingBaseline => "missing baseline".to_owned(),
+            TestStatus::Error => "error".to_owned(),
+        };
+        let approved = match read_history(observed_file.dir(), &case.name).last() {
+            Some(e) => format!("<br>{} by {}<br>{}", e.action, escape(&e.by), e.at),
+            None => String::new(),
+        };
+        let image = |kind: &str| {
+            format!(
+                "<td><a href=\"regression/{0}/{1}\"><img src=\"regression/{0}/{1}\" alt=\"{0}\"></a></td>",
+                kind, case.name
+            )
+        };
+        let approve = match approve_token.0 {
+            Some(_) => format!(
+                "<form method=\"post\" action=\"approve/{}\">\
+                <input type=\"password\" name=\"token\" placeholder=\"Approve token\" required>\
+                <button>Approve</button></form>",
+                case.name
+            ),
+            None => format!("<code>observer approve {}</code>", case.name),
+        };
+        table += &format!(
+            "    <tr><td>{}<br>{}{}</td><td>{}</td>{}{}{}<td>{}</td></tr>\n",
+            case.name,
+            escape(&case.url),
+            approved,
+            escape(&status),
+            image("baseline"),
+            image("current"),
+            image("diff"),
+            approve,
+        );
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(include_str!("../../regression_template.html").replacen('#', &table, 1))
+}
+
+#[get("/regression/{kind}/{name}")]
+pub async fn regression_capture(
+    path: Path<(String, String)>,
+    observed_file: Data<ObservedFileName>,
+) -> Result<NamedFile> {
+    let (kind, name) = path.into_inner();
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(ErrorNotFound("capture not found"));
+    }
+
+    // only the files of the test cases of the last run are served
+    let case = read_test_report(observed_file.dir())
+        .into_iter()
+        .find(|c| c.name == name);
+    let filename = match (&*kind, case) {
+        ("baseline", Some(c)) => Some(c.baseline),
+        ("current", Some(c)) => Some(c.current),
+        ("diff", Some(c)) => c.diff,
+        _ => None,
+    };
+
+    match filename {
+        Some(f) => Ok(NamedFile::open_async(observed_file.dir().join(f)).await?),
+        None => Err(ErrorNotFound("capture not found")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use std::fs;
+
+    #[actix_web::test]
+    async fn serves_only_test_case_captures() {
+        let dir = std::env::temp_dir().join(format!("observer_regression_{}", std::process::id()));
+        let cap = dir.join("cap");
+        fs::create_dir_all(cap.join("observed_test")).unwrap();
+        fs::create_dir_all(cap.join("observer_baselines")).unwrap();
+        fs::write(dir.join("secret.png"), "secret").unwrap();
+        fs::write(cap.join("observer_baselines/home.png"), "baseline").unwrap();
+        fs::write(cap.join("observer_baselines/other.png"), "other").unwrap();
+        let report = serde_json::json!([{
+            "name": "home", "target": "home", "url": "https://www.gnu.org", "browser": "chrome",
+            "viewport": "1920x1080", "status": "passed", "diff_percent": 0.0,
+            "baseline": "observer_baselines/home.png", "current": "observed_test/home.png",
+            "diff": null, "message": null, "duration_ms": 1.0,
+        }]);
+        fs::write(cap.join("observed_test/report.json"), report.to_string()).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(ObservedFileName::new(&cap, "png")))
+                .service(regression_capture),
+        )
+        .await;
+        let status = |uri: &'static str| {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            test::call_service(&app, req)
+        };
+        assert!(status("/regression/baseline/home")
+            .await
+            .status()
+            .is_success());
+        for uri in [
+            "/regression/baseline/..%2F..%2Fsecret",
+            "/regression/baseline/..%2Fsecret",
+            "/regression/baseline/other",
+            "/regression/current/other",
+        ] {
+            assert_eq!(status(uri).await.status(), 404, "{}", uri);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,4 @@
+mod get;
+mod post;
+pub use get::{regression, regression_capture};
+pub use post::approve_capture;
@@ -0,0 +1,54 @@
+use crate::baseline::approve;
+use crate::server::startup::{ApproveToken, ObservedFileName};
+use actix_web::error::{ErrorBadRequest, ErrorForbidden};
+use actix_web::http::header::LOCATION;
+use actix_web::web::{Data, Form, Path};
+use actix_web::{post, HttpRequest, HttpResponse, Result};
+
+#[derive(serde::Deserialize)]
+pub struct ApproveForm {
+    token: String,
+}
+
+#[post("/approve/{name}")]
+pub async fn approve_capture(
+    req: HttpRequest,
+    name: Path<String>,
+    form: Form<ApproveForm>,
+    approve_token: Data<ApproveToken>,
+    observed_file: Data<ObservedFileName>,
+) -> Result<HttpResponse> {
+    match &approve_token.0 {
+        None => {
+            return Err(ErrorForbidden(
+                "approving from the web server is disabled, set approve_token to enable it",
+            ))
+        }
+        Some(token) if !same_token(token, &form.token) => {
+            return Err(ErrorForbidden("invalid approve token"))
+        }
+        Some(_) => (),
+    }
+
+    let by = format!(
+        "web ({})",
+        req.connection_info()
+            .realip_remote_addr()
+            .unwrap_or("unknown")
+    );
+    approve(observed_file.dir(), &name, &by).map_err(|e| ErrorBadRequest(e.to_string()))?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "../regression"))
+        .finish())
+}
+
+// compares every byte so that the time taken does not tell how much of the token matched
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
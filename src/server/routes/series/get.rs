@@ -0,0 +1,32 @@
+use crate::series::read_samples;
+use crate::server::startup::ObservedFileName;
+use actix_web::web::{Data, Query};
+use actix_web::{get, HttpResponse};
+
+#[derive(serde::Deserialize)]
+pub struct SeriesQuery {
+    format: Option<String>,
+}
+
+#[get("/series")]
+pub async fn series(
+    query: Query<SeriesQuery>,
+    observed_file: Data<ObservedFileName>,
+) -> HttpResponse {
+    let samples = read_samples(observed_file.dir());
+    match query.format.as_deref() {
+        Some("csv") => {
+            let mut csv = "timestamp,name,value\n".to_owned();
+            for s in samples {
+                csv += &format!("{},{},{}\n", s.timestamp, s.name, s.value);
+            }
+
+            HttpResponse::Ok().content_type("text/csv").body(csv)
+        }
+        Some("json") | None => HttpResponse::Ok().json(samples),
+        Some(f) => HttpResponse::BadRequest().body(format!(
+            "series format must either be csv or json (got '{}')",
+            f
+        )),
+    }
+}
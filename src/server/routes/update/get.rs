@@ -1,9 +1,32 @@
 use crate::server::startup::ObservedFileName;
+use crate::steps::step_capture_filename;
 use actix_files::NamedFile;
-use actix_web::web::Data;
+use actix_web::error::ErrorNotFound;
+use actix_web::web::{Data, Path};
 use actix_web::{get, Result};
 
 #[get("/update")]
 pub async fn update(observed_file: Data<ObservedFileName>) -> Result<NamedFile> {
-    Ok(NamedFile::open_async(&observed_file.0).await?)
+    Ok(NamedFile::open_async(observed_file.name()).await?)
+}
+
+#[get("/update/{name}")]
+pub async fn update_step(
+    name: Path<String>,
+    observed_file: Data<ObservedFileName>,
+) -> Result<NamedFile> {
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(ErrorNotFound("capture not found"));
+    }
+
+    let file_format = observed_file.file_format();
+    Ok(NamedFile::open_async(step_capture_filename(
+        &observed_file.name(),
+        &name,
+        &file_format,
+    ))
+    .await?)
 }
@@ -1,2 +1,2 @@
 mod get;
-pub use get::update;
+pub use get::{update, update_step};
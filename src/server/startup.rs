@@ -1,32 +1,194 @@
-use crate::server::routes::{preview, update};
-use actix_web::web::Data;
+use crate::baseline::parse_approve_token;
+use crate::server::redirect::{redirect_to_https, HttpsPort};
+use crate::server::routes::{
+    approve_capture, captured, crawl, crawl_capture, har, matrix, matrix_capture, metadata,
+    preview, regression, regression_capture, series, update, update_step,
+};
+use crate::server::watcher::CaptureWatcher;
+use crate::settings::{parse_listeners, Listener};
+use crate::tls::{generate_self_signed, server_config, Tls};
+use actix_web::web::{self, Data};
 use actix_web::{rt, App, HttpServer};
 use actix_web_lab::web::redirect;
-use anyhow::bail;
-use std::net::TcpListener;
-use std::sync::mpsc;
+use anyhow::{anyhow, bail};
+use socket2::{Domain, Socket, Type};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, RwLock};
+use std::thread;
+
+// where the server listens and what it accepts from its clients
+pub struct ServerOptions<'a> {
+    pub server_ip: &'a str,
+    pub listeners: &'a [Listener],
+    pub tls: Option<&'a Tls>,
+    pub approve_token: Option<&'a str>,
+    pub capture_dir: &'a Path,
+}
 
 #[actix_web::main]
 pub async fn start_server(
-    server_ip: &String,
-    host: &String,
-    mut port: u16,
+    options: &ServerOptions,
     file_format: &str,
     interval: u16,
     status_channels: Option<(&mpsc::Sender<String>, &crossbeam_channel::Receiver<()>)>,
+    reload: Option<crossbeam_channel::Receiver<(String, u16)>>,
 ) -> anyhow::Result<()> {
-    let address = format!("{}:{}", host, port);
-    let listener = TcpListener::bind(&address)?;
-    port = listener.local_addr()?.port();
-
-    let preview_html = Data::new(PreviewHtmlContents::new(
+    let preview_html = Data::new(PreviewHtmlContents::new(file_format, interval));
+    let observed_file_format = Data::new(ObservedFileName::new(options.capture_dir, file_format));
+    let watcher = Data::new(CaptureWatcher::watch(observed_file_format.clone()));
+    let approve_token = match options.approve_token {
+        Some(t) => Some(parse_approve_token(t).map_err(anyhow::Error::msg)?),
+        None => None,
+    };
+    let approve_token = Data::new(ApproveToken(approve_token));
+    let ServerOptions {
         server_ip,
-        port,
-        file_format,
-        interval,
-    ));
-    let observed_file_format = Data::new(ObservedFileName(format!("observed.{}", file_format)));
-    let address_str = format!("Server address: {}:{}", server_ip, port);
+        listeners,
+        tls,
+        ..
+    } = *options;
+
+    // file format and interval of a reloaded configuration, the preview pages
+    // reload themselves when their version changes
+    if let Some(reload) = reload {
+        let preview_html = preview_html.clone();
+        let observed_file_format = observed_file_format.clone();
+        thread::spawn(move || {
+            for (file_format, interval) in reload {
+                observed_file_format.set(&file_format);
+                preview_html.update(&file_format, interval);
+            }
+        });
+    }
+
+    let mut srv = HttpServer::new(move || {
+        App::new()
+            .service(redirect("/", "preview"))
+            .service(preview)
+            .service(update)
+            .service(captured)
+            .service(update_step)
+            .service(series)
+            .service(metadata)
+            .service(har)
+            .service(matrix)
+            .service(matrix_capture)
+            .service(crawl)
+            .service(crawl_capture)
+            .service(regression)
+            .service(regression_capture)
+            .service(approve_capture)
+            .app_data(preview_html.clone())
+            .app_data(observed_file_format.clone())
+            .app_data(watcher.clone())
+            .app_data(approve_token.clone())
+    });
+
+    // listeners without a port share the one chosen by the OS for the first of them
+    let ipv4 = listeners
+        .iter()
+        .any(|l| matches!(l, Listener::Tcp(addr) if addr.is_ipv4()));
+    let mut random_port = None;
+    let mut addresses = Vec::with_capacity(listeners.len());
+    let mut tcp_addrs = Vec::new();
+
+    let tls_config = match tls {
+        Some(t) => {
+            if generate_self_signed(t, server_ip)? {
+                addresses.push(format!("Self-signed certificate: {}", t.cert_path()));
+            }
+            Some(server_config(t)?)
+        }
+        None => None,
+    };
+    let scheme = match tls_config {
+        Some(_) => "https://",
+        None => "",
+    };
+
+    for listener in listeners {
+        match listener {
+            Listener::Tcp(addr) => {
+                let mut addr = *addr;
+                if addr.port() == 0 {
+                    addr.set_port(random_port.unwrap_or(0));
+                }
+                let tcp = bind_tcp(addr, ipv4)
+                    .map_err(|e| anyhow!("Cannot listen on {}\n{}", addr, e))?;
+                let local_addr = tcp.local_addr()?;
+                if addr.port() == 0 {
+                    random_port = Some(local_addr.port());
+                }
+
+                // 0.0.0.0 and :: are printed with the same detected address
+                let address = format!(
+                    "Server address: {}{}",
+                    scheme,
+                    SocketAddr::new(printed_ip(server_ip, local_addr.ip()), local_addr.port())
+                );
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+                tcp_addrs.push(local_addr);
+                srv = match &tls_config {
+                    Some(config) => srv.listen_rustls(tcp, config.clone())?,
+                    None => srv.listen(tcp)?,
+                };
+            }
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                let uds =
+                    bind_unix(path).map_err(|e| anyhow!("Cannot listen on {}\n{}", listener, e))?;
+                addresses.push(format!("Server socket: {}", path.display()));
+                srv = srv.listen_uds(uds)?;
+            }
+            #[cfg(not(unix))]
+            Listener::Unix(_) => {
+                bail!("Unix domain sockets are not supported on this platform")
+            }
+        }
+    }
+
+    // plain HTTP on the addresses of the IP listeners, redirecting to the port of
+    // the first one
+    let mut redirect_srv = None;
+    if let Some(redirect_port) = tls.and_then(|t| t.redirect_port) {
+        let https_port = match tcp_addrs.first() {
+            Some(addr) => Data::new(HttpsPort(addr.port())),
+            None => bail!("tls redirect_port needs an IP address in host"),
+        };
+        let mut redirect = HttpServer::new(move || {
+            App::new()
+                .app_data(https_port.clone())
+                .default_service(web::to(redirect_to_https))
+        })
+        .workers(1);
+
+        let mut redirect_addrs = Vec::new();
+        for addr in &tcp_addrs {
+            let addr = SocketAddr::new(addr.ip(), redirect_port);
+            if redirect_addrs.contains(&addr) {
+                continue;
+            }
+            let tcp =
+                bind_tcp(addr, ipv4).map_err(|e| anyhow!("Cannot listen on {}\n{}", addr, e))?;
+            let address = format!(
+                "Redirect address: {}",
+                SocketAddr::new(printed_ip(server_ip, addr.ip()), redirect_port)
+            );
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+            redirect_addrs.push(addr);
+            redirect = redirect.listen(tcp)?;
+        }
+        redirect_srv = Some(redirect.run());
+    }
+
+    let address_str = addresses.join("\n");
     if let Some((tx, _)) = status_channels {
         if tx
             .send(format!(
@@ -42,17 +204,12 @@ pub async fn start_server(
         println!("{address_str}");
     }
 
-    let srv = HttpServer::new(move || {
-        App::new()
-            .service(redirect("/", "/preview"))
-            .service(preview)
-            .service(update)
-            .app_data(preview_html.clone())
-            .app_data(observed_file_format.clone())
-    })
-    .listen(listener)?
-    .run();
-
+    let srv = srv.run();
+    let redirect_handle = redirect_srv.map(|redirect| {
+        let handle = redirect.handle();
+        rt::spawn(redirect);
+        handle
+    });
     let mut rt = None;
     if let Some((tx, rx)) = status_channels {
         let srv_handle = srv.handle();
@@ -79,6 +236,14 @@ pub async fn start_server(
     }
 
     srv.await?;
+    if let Some(handle) = redirect_handle {
+        handle.stop(true).await;
+    }
+    for listener in listeners {
+        if let Listener::Unix(path) = listener {
+            let _ = fs::remove_file(path);
+        }
+    }
     if let Some(rt) = rt {
         rt.await??;
     }
@@ -86,17 +251,150 @@ pub async fn start_server(
     Ok(())
 }
 
-pub struct ObservedFileName(pub String);
+// IPv6 sockets usually accept IPv4 connections too, which conflicts with an IPv4
+// listener on the same port
+fn bind_tcp(addr: SocketAddr, only_v6: bool) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(only_v6)?;
+    }
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+
+    Ok(socket.into())
+}
+
+// a socket file left by a previous server would make the bind fail, it is only
+// removed when nothing accepts connections on it anymore
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use std::io::{Error, ErrorKind};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if let Ok(file) = fs::symlink_metadata(path) {
+        if file.file_type().is_socket() {
+            match UnixStream::connect(path) {
+                Ok(_) => {
+                    return Err(Error::new(
+                        ErrorKind::AddrInUse,
+                        "socket already in use by another server",
+                    ))
+                }
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => fs::remove_file(path)?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    UnixListener::bind(path)
+}
+
+// only printed, the preview pages use relative urls: server_ip when set, the
+// listener address when it is a specific one, otherwise the first IPv4 address
+// of a network interface
+fn printed_ip(server_ip: &str, ip: IpAddr) -> IpAddr {
+    if let Ok(server_ip) = server_ip.parse() {
+        return server_ip;
+    } else if !ip.is_unspecified() {
+        return ip;
+    }
+
+    if_addrs::get_if_addrs()
+        .ok()
+        .and_then(|interfaces| {
+            interfaces
+                .into_iter()
+                .find(|i| !i.is_loopback() && i.ip().is_ipv4())
+        })
+        .map_or(IpAddr::V4(Ipv4Addr::LOCALHOST), |i| i.ip())
+}
+
+// printed address of the first IP listener of host
+pub fn server_address(server_ip: &str, host: &str) -> String {
+    let ip = parse_listeners(host, 0)
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|l| match l {
+            Listener::Tcp(addr) => Some(addr.ip()),
+            Listener::Unix(_) => None,
+        })
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    printed_ip(server_ip, ip).to_string()
+}
+
+// the capture served by the preview, in the capture directory
+pub struct ObservedFileName {
+    dir: PathBuf,
+    name: RwLock<String>,
+}
+
+impl ObservedFileName {
+    pub fn new(dir: &Path, file_format: &str) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            name: RwLock::new(format!("observed.{}", file_format)),
+        }
+    }
+
+    fn set(&self, file_format: &str) {
+        if let Ok(mut name) = self.name.write() {
+            *name = format!("observed.{}", file_format);
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let name = self.name.read().map(|n| n.clone()).unwrap_or_default();
+        self.dir.join(name).to_string_lossy().into_owned()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn file_format(&self) -> String {
+        let name = self.name();
+        name.rsplit('.').next().unwrap_or_default().to_owned()
+    }
+}
+
+// approving from the web server is disabled without a token
+pub struct ApproveToken(pub Option<String>);
+
+pub struct PreviewHtmlContents {
+    html: RwLock<String>,
+    version: AtomicUsize,
+}
 
-pub struct PreviewHtmlContents(pub String);
 impl PreviewHtmlContents {
-    fn new(server_ip: &str, port: u16, file_format: &str, interval: u16) -> Self {
-        Self(
-            include_str!("preview_template.html")
-                .replacen('#', server_ip, 1)
-                .replacen('#', &port.to_string(), 1)
+    fn new(file_format: &str, interval: u16) -> Self {
+        let contents = Self {
+            html: RwLock::default(),
+            version: AtomicUsize::new(0),
+        };
+        contents.update(file_format, interval);
+
+        contents
+    }
+
+    fn update(&self, file_format: &str, interval: u16) {
+        let version = self.version.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Ok(mut html) = self.html.write() {
+            *html = include_str!("preview_template.html")
+                .replacen('#', &version.to_string(), 1)
                 .replacen('#', file_format, 1)
-                .replacen('#', &((interval as u64) * 1000 * 60).to_string(), 1),
-        )
+                .replacen('#', &((interval as u64) * 1000 * 60).to_string(), 1);
+        }
+    }
+
+    pub fn html(&self) -> String {
+        self.html.read().map(|h| h.clone()).unwrap_or_default()
+    }
+
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::SeqCst)
     }
 }
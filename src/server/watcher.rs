@@ -0,0 +1,36 @@
+use crate::server::startup::ObservedFileName;
+use actix_web::web::Data;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+const WATCH_PERIOD: Duration = Duration::from_secs(1);
+
+// modification time of the latest capture, the server does not need to run
+// alongside the observer as long as both share the capture directory
+#[derive(Clone, Default)]
+pub struct CaptureWatcher(pub Arc<Mutex<Option<String>>>);
+
+impl CaptureWatcher {
+    pub fn watch(observed_file: Data<ObservedFileName>) -> Self {
+        let watcher = Self::default();
+        let state = watcher.0.clone();
+        thread::spawn(move || loop {
+            let modified = fs::metadata(observed_file.name())
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339());
+            if let Ok(mut last) = state.lock() {
+                *last = modified;
+            }
+            sleep(WATCH_PERIOD);
+        });
+
+        watcher
+    }
+
+    pub fn last_capture(&self) -> Option<String> {
+        self.0.lock().ok()?.clone()
+    }
+}
@@ -1,73 +1,189 @@
-use crate::cli::Args;
-use anyhow::bail;
-use clap::Parser;
-use config::{Config, ConfigError, File, FileFormat};
+use crate::cli::{Args, Command};
+use crate::target::{parse_target, Target};
+use crate::tls::{parse_tls, Tls};
+use crate::validation::check_config;
+use anyhow::{anyhow, bail};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use config::{Config, Environment, File, FileFormat};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 
-#[derive(Default, serde::Deserialize)]
+pub const CONFIG_ENV: &str = "OBSERVER_CONFIG";
+pub const ENV_PREFIX: &str = "OBSERVER";
+pub const FILE_FORMATS: [&str; 3] = ["png", "jpeg", "jpg"];
+pub const TARGET_BROWSERS: [&str; 3] = ["chromium", "chrome", "edge"];
+
+#[derive(Default, serde::Deserialize, schemars::JsonSchema)]
 pub struct Settings {
+    #[schemars(
+        description = "IP printed in the server address (ex: 192.168.13.37), detected from the network interfaces by default"
+    )]
     pub server_ip: String,
+    #[schemars(
+        description = "Comma separated addresses the web server listens on: IPv4 or IPv6 addresses with an optional port (ex: 127.0.0.1, 0.0.0.0, ::, [::1]:8080) or unix:<path> for a Unix domain socket"
+    )]
     pub host: String,
+    #[schemars(description = "Port number of the web server, 0 lets the OS choose one")]
     pub port: u16,
+    #[schemars(description = "Browser used to capture the website")]
+    #[schemars(schema_with = "crate::schema::target_browser")]
     pub target_browser: String,
-    pub url: String,
+    #[serde(flatten)]
+    pub target: Target,
+    #[serde(default)]
+    #[schemars(
+        description = "Additional targets, observed and tested along with the top-level one"
+    )]
+    pub targets: Vec<Target>,
+    #[schemars(description = "File format of captures")]
+    #[schemars(schema_with = "crate::schema::file_format")]
     pub file_format: String,
+    #[schemars(description = "Interval in minutes between each browser capture")]
+    #[schemars(range(min = 1, max = 1440))]
     pub interval: u16,
+    #[schemars(description = "Do not print logs to terminal in CLI mode, same as -q")]
     pub quiet_flag: bool,
+    #[schemars(
+        description = "Launch the GUI instead of running the command, same as observer gui"
+    )]
     pub gui_mode: bool,
+    #[serde(default)]
+    #[schemars(description = "Directory where captures are written and served from")]
+    pub capture_dir: String,
+    #[schemars(description = "HTTPS for the web server, Unix domain sockets stay plain HTTP")]
+    pub tls: Option<Tls>,
+    #[serde(default)]
+    #[schemars(
+        description = "Token asked by the web server to approve captures, which it cannot do without one. Can reference an environment variable with ${VAR}"
+    )]
+    pub approve_token: Option<String>,
+    #[serde(skip)]
+    pub command: Option<Command>,
+    #[serde(skip)]
+    pub layers: Option<Layers>,
 }
 
 impl Settings {
     pub fn parse_settings() -> anyhow::Result<Self> {
-        let args = Args::parse();
-        if args.config_file {
-            return match read_config_file() {
-                Ok(mut s) => {
-                    if args.gui_mode {
-                        s.gui_mode = true;
-                    }
-
-                    parse_config_file([
-                        &mut s.server_ip,
-                        &mut s.host,
-                        &mut s.target_browser,
-                        &mut s.file_format,
-                    ])?;
-
-                    Ok(s)
-                }
-                Err(e) => Err(e.into()),
-            };
+        let matches = Args::command().get_matches();
+        let args = Args::from_arg_matches(&matches)?;
+        let use_file =
+            args.config_file || args.config.is_some() || env::var_os(CONFIG_ENV).is_some();
+        let layers = Layers::load(use_file, args.config.as_deref(), &cli_overrides(&matches))?;
+
+        // config show and config check report on invalid configurations themselves
+        if matches!(args.command, Command::Config { .. }) {
+            let mut s = layers.settings().unwrap_or_default();
+            s.command = Some(args.command);
+            s.layers = Some(layers);
+            return Ok(s);
         }
 
-        if !args.gui_mode {
-            if args.server_ip.is_none() {
-                bail!("--server-ip must be specified\nFor more information try --help");
-            } else if args.url.is_none() {
-                bail!("--url must be specified\nFor more information try --help");
-            }
-        } else {
-            return Ok(Self {
-                gui_mode: true,
-                ..Default::default()
-            });
+        let mut s = Self::from_layers(layers)?;
+        if matches!(args.command, Command::Gui) {
+            s.gui_mode = true;
         }
 
-        Ok(Self {
-            host: args.host,
-            server_ip: args.server_ip.unwrap(),
-            port: args.port,
-            target_browser: args.target_browser,
-            url: args.url.unwrap(),
-            file_format: args.file_format,
-            interval: args.interval,
-            quiet_flag: args.quiet,
-            gui_mode: args.gui_mode,
-        })
+        let needs_url = matches!(
+            &args.command,
+            Command::Observe { .. }
+                | Command::Capture { .. }
+                | Command::Test { .. }
+                | Command::Run { .. }
+        );
+        if needs_url && s.target.url.is_empty() {
+            bail!(
+                "--url must be specified (or url in the configuration file or {}_URL)\n\
+                For more information try --help",
+                ENV_PREFIX
+            );
+        }
+
+        s.command = Some(args.command);
+
+        Ok(s)
+    }
+
+    fn from_layers(layers: Layers) -> anyhow::Result<Self> {
+        let issues = check_config(&layers)?;
+        if !issues.is_empty() {
+            bail!(
+                "Invalid configuration:\n{}",
+                issues
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        let mut s = layers.settings()?;
+        parse_config_file([&mut s.host, &mut s.target_browser, &mut s.file_format])?;
+        if !s.server_ip.is_empty() {
+            s.server_ip = parse_server_ip(&s.server_ip).map_err(|e| anyhow!(e))?;
+        }
+        s.layers = Some(layers);
+
+        Ok(s)
+    }
+
+    // the same layers read again, the command is not kept
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        match &self.layers {
+            Some(layers) => Self::from_layers(layers.reload()?),
+            None => bail!("Settings were not read from configuration layers"),
+        }
+    }
+
+    // absolute path of the configuration file, if one is read
+    pub fn config_file(&self) -> Option<&Path> {
+        self.layers.as_ref()?.reload_path.as_deref()
+    }
+
+    // captures are written to and served from the working directory by default
+    pub fn capture_path(&self) -> &Path {
+        match self.capture_dir.is_empty() {
+            true => Path::new("."),
+            false => Path::new(&self.capture_dir),
+        }
+    }
+
+    // the top-level target followed by the ones listed under targets
+    // targets without a proxy of their own go through the top-level one (or --proxy)
+    pub fn inherit_proxy(&mut self) {
+        for t in self.targets.iter_mut().filter(|t| t.proxy.is_none()) {
+            t.proxy = self.target.proxy.clone();
+        }
+    }
+
+    pub fn all_targets(&self) -> Vec<&Target> {
+        std::iter::once(&self.target)
+            .chain(self.targets.iter())
+            .collect()
+    }
+}
+
+fn parse_targets(s: &Settings) -> Result<(), String> {
+    let mut names = Vec::new();
+    for t in s.all_targets() {
+        parse_target(t)?;
+        let name = t.name();
+        if names.contains(&name) {
+            return Err(format!("target name '{}' is used more than once", name));
+        }
+        names.push(name);
     }
+
+    Ok(())
 }
 
 pub fn load_config_gui_mode() -> Result<Settings, String> {
-    let mut s = match read_config_file() {
+    let mut s = match Layers::load(true, None, &[]).and_then(|l| l.settings()) {
         Ok(s) => s,
         Err(e) => return Err(e.to_string()),
     };
@@ -75,15 +191,19 @@ pub fn load_config_gui_mode() -> Result<Settings, String> {
     s.gui_mode = true;
     s.file_format = parse_file_format(&s.file_format)?;
     s.target_browser = parse_target_browser(&s.target_browser)?;
-    s.url = parse_url(&s.url)?;
+    s.target.url = parse_url(&s.target.url)?;
+    parse_targets(&s)?;
     s.host = parse_host(&s.host)?;
+    if let Some(t) = &s.tls {
+        parse_tls(t)?;
+    }
 
     Ok(s)
 }
 
 pub fn parse_file_format(f: &str) -> Result<String, String> {
     let format = f.trim().to_lowercase();
-    if !["png", "jpeg", "jpg"].contains(&&*format) {
+    if !FILE_FORMATS.contains(&&*format) {
         return Err("file format must either be JPEG or PNG".to_owned());
     }
 
@@ -92,7 +212,7 @@ pub fn parse_file_format(f: &str) -> Result<String, String> {
 
 pub fn parse_target_browser(t: &str) -> Result<String, String> {
     let target = t.trim().to_lowercase();
-    if !["chromium", "chrome", "edge"].contains(&&*target) {
+    if !TARGET_BROWSERS.contains(&&*target) {
         return Err("capture targets available are chromium, chrome and edge".to_owned());
     }
 
@@ -109,36 +229,86 @@ pub fn parse_url(u: &str) -> Result<String, String> {
     Ok(url)
 }
 
-pub fn parse_host(h: &str) -> Result<String, String> {
-    let host = h.trim().to_lowercase();
-    if !["127.0.0.1", "0.0.0.0"].contains(&&*host) {
-        return Err(
-            "host can either be 127.0.0.1 to serve locally on the host computer\n\
-        or 0.0.0.0 to serve on the computer network"
-                .to_owned(),
-        );
+pub fn parse_interval(i: &str) -> Result<u16, String> {
+    match i.trim().parse::<u16>() {
+        Ok(interval) if (1..=1440).contains(&interval) => Ok(interval),
+        _ => Err("interval must be between 1 and 1440 minutes".to_owned()),
     }
+}
 
-    Ok(host)
+pub fn parse_host(h: &str) -> Result<String, String> {
+    parse_listeners(h, 0)?;
+
+    Ok(h.split(',').map(str::trim).collect::<Vec<_>>().join(","))
 }
 
 pub fn parse_server_ip(i: &str) -> Result<String, String> {
-    let server_ip = i.trim().to_lowercase();
-    if !validator::validate_ip_v4(&server_ip) {
-        return Err("server IP must be an IPv4 address".to_owned());
+    match i.trim().parse::<IpAddr>() {
+        Ok(ip) => Ok(ip.to_string()),
+        Err(_) => Err("server IP must be an IPv4 or IPv6 address".to_owned()),
     }
+}
 
-    Ok(server_ip)
+// an address the web server listens on
+#[derive(Clone, PartialEq)]
+pub enum Listener {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
 }
 
-fn parse_config_file(str_config: [&mut String; 4]) -> anyhow::Result<()> {
-    let parsing_func = [
-        parse_server_ip,
-        parse_host,
-        parse_target_browser,
-        parse_file_format,
-    ];
-    for i in 0..4 {
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Listener::Tcp(addr) => write!(f, "{}", addr),
+            Listener::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+// addresses without a port use the port setting
+pub fn parse_listeners(host: &str, port: u16) -> Result<Vec<Listener>, String> {
+    let listeners: Option<Vec<Listener>> = host
+        .split(',')
+        .map(|entry| parse_listener(entry.trim(), port))
+        .collect();
+    let listeners = listeners.ok_or_else(|| {
+        "host must be a comma separated list of IP addresses\n\
+        (ex: 127.0.0.1, 0.0.0.0, ::, [::1]:8080) or unix:<path>\n\
+        to listen on a Unix domain socket"
+            .to_owned()
+    })?;
+
+    // the second bind of the same address would fail
+    for (i, listener) in listeners.iter().enumerate() {
+        if listeners[..i].contains(listener) {
+            return Err(format!("host lists {} more than once", listener));
+        }
+    }
+
+    Ok(listeners)
+}
+
+fn parse_listener(entry: &str, port: u16) -> Option<Listener> {
+    if let Some(path) = entry.strip_prefix("unix:") {
+        return match path.is_empty() {
+            true => None,
+            false => Some(Listener::Unix(PathBuf::from(path))),
+        };
+    }
+
+    let ip = entry
+        .strip_prefix('[')
+        .and_then(|e| e.strip_suffix(']'))
+        .unwrap_or(entry);
+    match ip.parse::<IpAddr>() {
+        Ok(ip) => Some(Listener::Tcp(SocketAddr::new(ip, port))),
+        Err(_) => entry.parse().ok().map(Listener::Tcp),
+    }
+}
+
+fn parse_config_file(str_config: [&mut String; 3]) -> anyhow::Result<()> {
+    let parsing_func = [parse_host, parse_target_browser, parse_file_format];
+    for i in 0..3 {
         match parsing_func[i](str_config[i]) {
             Ok(ret) => {
                 str_config[i].clear();
@@ -166,8 +336,7 @@ pub fn parse_config_gui_mode(
             if observer_button {
                 err_str += "observer\n[URL, Target Browser, File Format, Saving Interval]";
             } else {
-                err_str +=
-                    "server\n[File Format, Saving Interval, Server IP, Host Address, Port Number]";
+                err_str += "server\n[File Format, Saving Interval, Host Address, Port Number]";
             }
 
             if config_file_mode {
@@ -193,9 +362,315 @@ pub fn parse_config_gui_mode(
     Ok(())
 }
 
-fn read_config_file() -> Result<Settings, ConfigError> {
-    Config::builder()
-        .add_source(File::new("config", FileFormat::Yaml))
-        .build()?
-        .try_deserialize()
+// explicit path, then OBSERVER_CONFIG, then the first default location found
+pub fn find_config_file(path: Option<&str>) -> anyhow::Result<PathBuf> {
+    if let Some(p) = path {
+        return Ok(PathBuf::from(p));
+    } else if let Ok(p) = env::var(CONFIG_ENV) {
+        return Ok(PathBuf::from(p));
+    }
+
+    let mut paths = vec![
+        PathBuf::from("./config"),
+        PathBuf::from("./config.yaml"),
+        PathBuf::from("./config.yml"),
+        PathBuf::from("./config.toml"),
+        PathBuf::from("./config.json"),
+    ];
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var("HOME").ok().map(|h| Path::new(&h).join(".config")));
+    if let Some(dir) = config_home {
+        paths.push(dir.join("observer/config.yaml"));
+    }
+    paths.push(PathBuf::from("/etc/observer/config.yaml"));
+
+    match paths.iter().find(|p| p.is_file()) {
+        Some(p) => Ok(p.clone()),
+        None => bail!(
+            "No configuration file found, searched:\n{}",
+            paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+// the format is detected from the extension, files without one are read as YAML
+fn config_file_format(path: &Path) -> anyhow::Result<FileFormat> {
+    Ok(match path.extension().and_then(|e| e.to_str()) {
+        None | Some("yaml") | Some("yml") => FileFormat::Yaml,
+        Some("toml") => FileFormat::Toml,
+        Some("json") => FileFormat::Json,
+        Some(e) => bail!(
+            "Configuration file format '{}' is not supported (yaml, yml, toml, json)",
+            e
+        ),
+    })
+}
+
+// (argument id, settings key) of the command line flags overriding other layers,
+// clap ids of derived args are kebab-case
+const CLI_OVERRIDES: [(&str, &str); 9] = [
+    ("url", "url"),
+    ("target-browser", "target_browser"),
+    ("file-format", "file_format"),
+    ("proxy", "proxy.server"),
+    ("host", "host"),
+    ("port", "port"),
+    ("server-ip", "server_ip"),
+    ("interval", "interval"),
+    ("capture-dir", "capture_dir"),
+];
+
+// only flags given explicitly override, clap default values are ignored
+fn cli_overrides(matches: &ArgMatches) -> Vec<(String, String)> {
+    let mut overrides = Vec::new();
+    let mut m = matches;
+    loop {
+        // value_source asserts the id exists on this (sub)command
+        let given = |id| {
+            m.try_contains_id(id).unwrap_or(false)
+                && m.value_source(id) == Some(ValueSource::CommandLine)
+        };
+        if given("quiet") {
+            overrides.push(("quiet_flag".to_owned(), "true".to_owned()));
+        }
+        for (id, key) in CLI_OVERRIDES {
+            if !given(id) {
+                continue;
+            }
+            if let Ok(Some(mut values)) = m.try_get_raw(id) {
+                if let Some(v) = values.next() {
+                    overrides.push((key.to_owned(), v.to_string_lossy().into_owned()));
+                }
+            }
+        }
+
+        match m.subcommand() {
+            Some((_, sub)) => m = sub,
+            None => return overrides,
+        }
+    }
+}
+
+// defaults < configuration file < OBSERVER_* environment variables < command line flags
+pub struct Layers {
+    config: Config,
+    sources: BTreeMap<String, String>,
+    // path and contents of the configuration file, read again by config check
+    file: Option<(PathBuf, String)>,
+    // absolute path of the file and command line flags to load the same layers again
+    reload_path: Option<PathBuf>,
+    overrides: Vec<(String, String)>,
+}
+
+impl Layers {
+    pub fn load(
+        use_file: bool,
+        path: Option<&str>,
+        overrides: &[(String, String)],
+    ) -> anyhow::Result<Self> {
+        let mut sources = BTreeMap::new();
+        let mut builder = Config::builder()
+            .set_default("server_ip", "")?
+            .set_default("host", "0.0.0.0")?
+            .set_default("port", 0)?
+            .set_default("target_browser", "chrome")?
+            .set_default("url", "")?
+            .set_default("file_format", "jpeg")?
+            .set_default("interval", 5)?
+            .set_default("quiet_flag", false)?
+            .set_default("gui_mode", false)?
+            .set_default("capture_dir", "")?;
+        for key in [
+            "server_ip",
+            "host",
+            "port",
+            "target_browser",
+            "url",
+            "file_format",
+            "interval",
+            "quiet_flag",
+            "gui_mode",
+            "capture_dir",
+        ] {
+            sources.insert(key.to_owned(), "default".to_owned());
+        }
+
+        let mut config_file = None;
+        let mut reload_path = None;
+        if use_file {
+            let path = find_config_file(path)?;
+            let file = File::from(path.as_path()).format(config_file_format(&path)?);
+            let keys = Config::builder()
+                .add_source(file.clone())
+                .build()
+                .and_then(|c| c.try_deserialize::<BTreeMap<String, config::Value>>())
+                .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            for key in keys.into_keys() {
+                sources.insert(key, path.display().to_string());
+            }
+            builder = builder.add_source(file);
+            let text = fs::read_to_string(&path)?;
+            reload_path = Some(env::current_dir()?.join(&path));
+            config_file = Some((path, text));
+        }
+
+        // OBSERVER_CONFIG names the file, it is not a setting
+        let env_prefix = format!("{}_", ENV_PREFIX);
+        let vars: config::Map<String, String> =
+            env::vars().filter(|(var, _)| var != CONFIG_ENV).collect();
+        for var in vars.keys() {
+            if let Some(key) = var.strip_prefix(&env_prefix) {
+                if !key.is_empty() {
+                    let key = key.split("__").next().unwrap_or_default().to_lowercase();
+                    sources.insert(key, format!("environment ({})", var));
+                }
+            }
+        }
+        builder = builder.add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("_")
+                .separator("__")
+                .try_parsing(true)
+                .source(Some(vars)),
+        );
+
+        for (key, value) in overrides {
+            let top_key = key.split('.').next().unwrap_or_default();
+            sources.insert(top_key.to_owned(), "command line".to_owned());
+            builder = match value.parse::<i64>() {
+                Ok(n) => builder.set_override(key.as_str(), n)?,
+                Err(_) => builder.set_override(key.as_str(), value.as_str())?,
+            };
+        }
+
+        Ok(Self {
+            config: builder.build()?,
+            sources,
+            file: config_file,
+            reload_path,
+            overrides: overrides.to_vec(),
+        })
+    }
+
+    // the configuration file found at startup is read again
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        let path = self.reload_path.as_ref().and_then(|p| p.to_str());
+        Self::load(path.is_some(), path, &self.overrides)
+    }
+
+    pub fn value(&self) -> anyhow::Result<serde_json::Value> {
+        Ok(self.config.clone().try_deserialize()?)
+    }
+
+    pub fn file(&self) -> Option<(&Path, &str)> {
+        self.file.as_ref().map(|(p, t)| (p.as_path(), t.as_str()))
+    }
+
+    pub fn source(&self, key: &str) -> Option<&str> {
+        self.sources.get(key).map(|s| s.as_str())
+    }
+
+    pub fn settings(&self) -> anyhow::Result<Settings> {
+        let mut s: Settings = self.config.clone().try_deserialize()?;
+        s.inherit_proxy();
+        Ok(s)
+    }
+
+    // every key of the effective configuration with the layer it comes from
+    pub fn show(&self) -> anyhow::Result<String> {
+        let mut values: BTreeMap<String, serde_json::Value> =
+            self.config.clone().try_deserialize()?;
+        for (key, value) in values.iter_mut() {
+            redact(key, value);
+        }
+
+        Ok(values
+            .iter()
+            .map(|(key, value)| {
+                let source = self.sources.get(key).map_or("default", |s| s.as_str());
+                format!("{} = {} # {}", key, value, source)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+// passwords, tokens, header and cookie values are replaced in the printed configuration,
+// wherever they are set (top-level or in targets)
+fn redact(key: &str, value: &mut serde_json::Value) {
+    let hide = |v: &mut serde_json::Value| {
+        if !v.is_null() {
+            *v = serde_json::Value::from("<redacted>");
+        }
+    };
+    match (key, value) {
+        ("password" | "approve_token", v) => hide(v),
+        ("headers", serde_json::Value::Object(headers)) => headers.values_mut().for_each(hide),
+        ("cookies", serde_json::Value::Array(cookies)) => {
+            for cookie in cookies.iter_mut().filter_map(|c| c.as_object_mut()) {
+                cookie.get_mut("value").map(hide);
+            }
+        }
+        ("proxy", serde_json::Value::Object(proxy)) => {
+            for k in ["username", "password"] {
+                proxy.get_mut(k).map(hide);
+            }
+        }
+        (_, serde_json::Value::Object(o)) => o.iter_mut().for_each(|(k, v)| redact(k, v)),
+        (_, serde_json::Value::Array(a)) => a.iter_mut().for_each(|v| redact("", v)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn targets_inherit_the_proxy() {
+        let mut s: Settings = serde_json::from_value(json!({
+            "server_ip": "",
+            "host": "0.0.0.0",
+            "port": 0,
+            "target_browser": "chrome",
+            "url": "https://www.gnu.org",
+            "file_format": "jpeg",
+            "interval": 5,
+            "quiet_flag": false,
+            "gui_mode": false,
+            "capture_dir": "",
+            "proxy": {"server": "socks5://10.0.0.2:1080"},
+            "targets": [
+                {"name": "pricing", "url": "https://example.com/pricing"},
+                {
+                    "name": "docs",
+                    "url": "https://example.com/docs",
+                    "proxy": {"server": "http://10.0.0.3:3128"},
+                },
+            ],
+        }))
+        .unwrap();
+        s.inherit_proxy();
+        let servers: Vec<_> = s
+            .all_targets()
+            .iter()
+            .map(|t| t.proxy.as_ref().map(|p| p.server.as_str()))
+            .collect();
+        assert_eq!(
+            servers,
+            [
+                Some("socks5://10.0.0.2:1080"),
+                Some("socks5://10.0.0.2:1080"),
+                Some("http://10.0.0.3:3128"),
+            ]
+        );
+    }
 }
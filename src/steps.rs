@@ -0,0 +1,160 @@
+use crate::auth::expand_env;
+use crate::inject::{apply_injection, Inject};
+use crate::settings::parse_url;
+use anyhow::anyhow;
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+use headless_chrome::Tab;
+use std::fs;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+pub const MAX_WAIT_SECONDS: f64 = 3600.0;
+
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    #[schemars(description = "Navigate to a url")]
+    Navigate(String),
+    #[schemars(description = "Click the element matching a CSS selector")]
+    Click(String),
+    #[schemars(
+        description = "Type text, which can reference an environment variable with ${VAR}, in the element matching a CSS selector"
+    )]
+    Type { selector: String, text: String },
+    #[schemars(description = "Press a key")]
+    Press(String),
+    #[schemars(description = "Wait for a number of seconds, at most 3600")]
+    Wait(f64),
+    #[schemars(description = "Wait for an element matching a CSS selector")]
+    WaitFor(String),
+    #[schemars(description = "Scroll to the element matching a CSS selector")]
+    Scroll(String),
+    #[schemars(description = "Scroll by a number of pixels")]
+    ScrollBy(i64),
+    #[schemars(description = "Evaluate JavaScript in the page")]
+    Evaluate(String),
+    #[schemars(description = "Capture the page under a name, served at /update/<name>")]
+    Screenshot(String),
+}
+
+pub fn parse_steps(steps: &[Step]) -> Result<(), (String, String)> {
+    let mut names = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        parse_step(step, &mut names).map_err(|e| (format!("[{}]", i), e))?;
+    }
+
+    Ok(())
+}
+
+fn parse_step<'a>(step: &'a Step, names: &mut Vec<&'a String>) -> Result<(), String> {
+    match step {
+        Step::Navigate(url) => {
+            parse_url(url)?;
+        }
+        Step::Type { text, .. } => {
+            expand_env(text)?;
+        }
+        Step::Wait(seconds) if !seconds.is_finite() || *seconds < 0.0 => {
+            return Err("wait step duration must be positive".to_owned());
+        }
+        Step::Wait(seconds) if *seconds > MAX_WAIT_SECONDS => {
+            return Err(format!(
+                "wait step duration must not exceed {} seconds",
+                MAX_WAIT_SECONDS
+            ));
+        }
+        Step::Screenshot(name) => {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(format!(
+                    "screenshot name '{}' must only contain letters, digits, '_' and '-'",
+                    name
+                ));
+            } else if names.contains(&name) {
+                return Err(format!("screenshot name '{}' is used more than once", name));
+            }
+            names.push(name);
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+// named after the capture the steps belong to, observed_<name>.<format> for observed.<format>
+pub fn step_capture_filename(capture_filename: &str, name: &str, file_format: &str) -> String {
+    let capture = Path::new(capture_filename);
+    let stem = capture.file_stem().unwrap_or_default().to_string_lossy();
+    capture
+        .with_file_name(format!("{}_{}.{}", stem, name, file_format))
+        .to_string_lossy()
+        .into_owned()
+}
+
+// runs the steps in order on an already navigated tab and
+// returns the filenames of the captures taken by screenshot steps
+pub fn run_steps(
+    tab: &Tab,
+    steps: &[Step],
+    inject: &Inject,
+    fmt: &CaptureScreenshotFormatOption,
+    capture_filename: &str,
+    file_format: &str,
+) -> anyhow::Result<Vec<String>> {
+    let mut captures = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let step_err = |e: anyhow::Error| anyhow!("Error: Step {} failed\n{}", i + 1, e);
+        match step {
+            Step::Navigate(url) => {
+                tab.navigate_to(url)
+                    .and_then(|t| t.wait_until_navigated())
+                    .and_then(|t| apply_injection(t, inject))
+                    .map_err(step_err)?;
+            }
+            Step::Click(selector) => {
+                tab.wait_for_element(selector)
+                    .and_then(|el| el.click().map(|_| ()))
+                    .map_err(step_err)?;
+            }
+            Step::Type { selector, text } => {
+                let text = expand_env(text).map_err(|e| step_err(anyhow!(e)))?;
+                tab.wait_for_element(selector)
+                    .and_then(|el| el.type_into(&text).map(|_| ()))
+                    .map_err(step_err)?;
+            }
+            Step::Press(key) => {
+                tab.press_key(key).map_err(step_err)?;
+            }
+            Step::Wait(seconds) => sleep(Duration::from_secs_f64(*seconds)),
+            Step::WaitFor(selector) => {
+                tab.wait_for_element(selector).map_err(step_err)?;
+            }
+            Step::Scroll(selector) => {
+                tab.wait_for_element(selector)
+                    .and_then(|el| el.scroll_into_view().map(|_| ()))
+                    .map_err(step_err)?;
+            }
+            Step::ScrollBy(pixels) => {
+                tab.evaluate(&format!("window.scrollBy(0, {})", pixels), false)
+                    .map_err(step_err)?;
+            }
+            Step::Evaluate(script) => {
+                tab.evaluate(script, true).map_err(step_err)?;
+            }
+            Step::Screenshot(name) => {
+                let filename = step_capture_filename(capture_filename, name, file_format);
+                let image_data = tab
+                    .capture_screenshot(fmt.clone(), Some(100), None, false)
+                    .map_err(step_err)?;
+                fs::write(&filename, image_data)?;
+                captures.push(filename);
+            }
+        }
+    }
+
+    Ok(captures)
+}
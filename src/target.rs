@@ -0,0 +1,136 @@
+use crate::auth::{parse_auth, Auth};
+use crate::checks::{parse_checks, Checks};
+use crate::crawl::{parse_crawl, Crawl};
+use crate::emulation::{parse_emulation, Emulation};
+use crate::inject::Inject;
+use crate::matrix::{parse_matrix, Matrix};
+use crate::proxy::{parse_proxy, Proxy};
+use crate::regression::{parse_regression, Regression};
+use crate::series::{parse_extractors, Extractor};
+use crate::steps::{parse_steps, Step};
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct Target {
+    #[schemars(
+        description = "Name used for baselines and reports (letters, digits, '_' and '-'), derived from the url by default"
+    )]
+    pub name: Option<String>,
+    #[schemars(description = "URL of the website to observe (ex: https://www.gnu.org)")]
+    pub url: String,
+    #[serde(default)]
+    #[schemars(description = "Numeric values extracted from the page at each capture")]
+    pub extractors: Vec<Extractor>,
+    #[serde(default)]
+    #[schemars(description = "Checks of the main document response")]
+    pub checks: Checks,
+    #[serde(default)]
+    #[schemars(description = "Export the network activity of each capture as a HAR file")]
+    pub har: bool,
+    #[serde(default)]
+    #[schemars(description = "Cookies, headers and basic auth applied before navigating")]
+    pub auth: Auth,
+    #[serde(default)]
+    #[schemars(description = "Actions run on the page before the capture")]
+    pub steps: Vec<Step>,
+    #[serde(default)]
+    #[schemars(description = "CSS and JavaScript injected before the capture")]
+    pub inject: Inject,
+    #[serde(default)]
+    #[schemars(description = "Device and locale emulation")]
+    pub emulation: Emulation,
+    #[schemars(description = "Proxy server used by the target browser")]
+    pub proxy: Option<Proxy>,
+    #[schemars(
+        description = "Viewports, browsers and color schemes captured in addition to the main capture"
+    )]
+    pub matrix: Option<Matrix>,
+    #[schemars(description = "Capture the pages linked from the url (chromium/chrome only)")]
+    pub crawl: Option<Crawl>,
+    #[serde(default)]
+    #[schemars(description = "Comparison of test captures with their baselines")]
+    pub regression: Regression,
+}
+
+impl Target {
+    // explicit name or one derived from the url, used for baselines and reports
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+
+        let url = self.url.split_once("://").map_or(&*self.url, |(_, u)| u);
+        let name: String = url
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        name.trim_matches('_').to_owned()
+    }
+}
+
+pub fn parse_target(t: &Target) -> Result<(), String> {
+    match target_errors(t).into_iter().next() {
+        Some((_, e)) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// every invalid value of the target, keyed by its path relative to the target:
+// the validators report the path of the value relative to their field
+pub fn target_errors(t: &Target) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+    let mut check = |key: &str, result: Result<(), (String, String)>| {
+        if let Err((path, e)) = result {
+            let path = match path.chars().next() {
+                None => key.to_owned(),
+                Some('[') => format!("{}{}", key, path),
+                Some(_) => format!("{}.{}", key, path),
+            };
+            errors.push((path, e));
+        }
+    };
+
+    if let Some(name) = &t.name {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            check(
+                "name",
+                Err((
+                    String::new(),
+                    format!(
+                        "target name '{}' must only contain letters, digits, '_' and '-'",
+                        name
+                    ),
+                )),
+            );
+        }
+    }
+    check("extractors", parse_extractors(&t.extractors));
+    check("checks", parse_checks(&t.checks));
+    check("auth", parse_auth(&t.auth));
+    check("steps", parse_steps(&t.steps));
+    check("emulation", parse_emulation(&t.emulation));
+    if let Some(m) = &t.matrix {
+        check("matrix", parse_matrix(m, t));
+    }
+    if let Some(c) = &t.crawl {
+        check("crawl", parse_crawl(c));
+    }
+    check("regression", parse_regression(&t.regression));
+    if let Some(p) = &t.proxy {
+        check("proxy", parse_proxy(p));
+        if p.has_credentials() && t.auth.basic_auth.is_some() {
+            check(
+                "proxy",
+                Err((
+                    String::new(),
+                    "proxy credentials cannot be combined with basic auth".to_owned(),
+                )),
+            );
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,192 @@
+use crate::settings::Listener;
+use anyhow::{anyhow, bail};
+use rcgen::{Certificate, CertificateParams, DnType, SanType};
+use rustls::{PrivateKey, ServerConfig};
+use rustls_pemfile::Item;
+use std::fs;
+use std::io::{BufReader, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+pub const SELF_SIGNED_CERT: &str = "./observer_cert.pem";
+pub const SELF_SIGNED_KEY: &str = "./observer_key.pem";
+
+#[derive(Clone, PartialEq, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct Tls {
+    #[schemars(description = "PEM file of the certificate chain")]
+    pub cert: Option<String>,
+    #[schemars(description = "PEM file of the private key (PKCS#8, RSA or EC)")]
+    pub key: Option<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "Generate a self-signed certificate when the cert and key files do not exist (observer_cert.pem and observer_key.pem of the working directory by default)"
+    )]
+    pub self_signed: bool,
+    #[schemars(description = "Port of a plain HTTP listener redirecting to HTTPS")]
+    pub redirect_port: Option<u16>,
+}
+
+impl Tls {
+    pub fn cert_path(&self) -> &str {
+        self.cert.as_deref().unwrap_or(SELF_SIGNED_CERT)
+    }
+
+    pub fn key_path(&self) -> &str {
+        self.key.as_deref().unwrap_or(SELF_SIGNED_KEY)
+    }
+}
+
+pub fn parse_tls(t: &Tls) -> Result<(), String> {
+    if t.cert.is_some() != t.key.is_some() {
+        return Err("tls cert and key must be specified together".to_owned());
+    }
+    if t.cert.is_none() && !t.self_signed {
+        return Err("tls needs a cert and a key, or self_signed".to_owned());
+    }
+    if t.redirect_port == Some(0) {
+        return Err("tls redirect port must not be 0".to_owned());
+    }
+
+    Ok(())
+}
+
+// the redirect listeners share the addresses of the HTTPS ones
+pub fn parse_redirect_port(t: &Tls, listeners: &[Listener]) -> Result<(), String> {
+    let port = match t.redirect_port {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    for listener in listeners {
+        if matches!(listener, Listener::Tcp(addr) if addr.port() == port) {
+            return Err(format!(
+                "tls redirect port {} is already used by {}",
+                port, listener
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// written once and reused afterwards, so that browsers can trust it, valid for
+// localhost, the network interfaces and server_ip
+pub fn generate_self_signed(t: &Tls, server_ip: &str) -> anyhow::Result<bool> {
+    if !t.self_signed || Path::new(t.cert_path()).exists() || Path::new(t.key_path()).exists() {
+        return Ok(false);
+    }
+
+    let mut ips: Vec<IpAddr> = if_addrs::get_if_addrs()
+        .map(|interfaces| interfaces.iter().map(|i| i.ip()).collect())
+        .unwrap_or_default();
+    if let Ok(ip) = server_ip.parse() {
+        ips.push(ip);
+    }
+    ips.sort();
+    ips.dedup();
+
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "observer self-signed");
+    params.subject_alt_names = std::iter::once(SanType::DnsName("localhost".to_owned()))
+        .chain(ips.into_iter().map(SanType::IpAddress))
+        .collect();
+    let cert = Certificate::from_params(params)?;
+
+    fs::write(t.cert_path(), cert.serialize_pem()?)?;
+    write_private(t.key_path(), &cert.serialize_private_key_pem())?;
+
+    Ok(true)
+}
+
+// only readable by the user running the server
+fn write_private(path: &str, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+pub fn server_config(t: &Tls) -> anyhow::Result<ServerConfig> {
+    let (cert_path, key_path) = (t.cert_path(), t.key_path());
+    let read = |path: &str| {
+        fs::File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| anyhow!("Cannot read {}\n{}", path, e))
+    };
+
+    let certs: Vec<rustls::Certificate> = rustls_pemfile::certs(&mut read(cert_path)?)?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+    if certs.is_empty() {
+        bail!("No certificate found in {}", cert_path);
+    }
+
+    let key = rustls_pemfile::read_all(&mut read(key_path)?)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(k) | Item::RSAKey(k) | Item::ECKey(k) => Some(PrivateKey(k)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No private key found in {}", key_path))?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| anyhow!("Invalid certificate or key\n{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::parse_listeners;
+
+    fn tls(cert: Option<&str>, key: Option<&str>, self_signed: bool) -> Tls {
+        Tls {
+            cert: cert.map(str::to_owned),
+            key: key.map(str::to_owned),
+            self_signed,
+            redirect_port: None,
+        }
+    }
+
+    #[test]
+    fn certificates() {
+        assert!(parse_tls(&tls(Some("cert.pem"), Some("key.pem"), false)).is_ok());
+        assert!(parse_tls(&tls(None, None, true)).is_ok());
+        assert!(parse_tls(&tls(Some("cert.pem"), Some("key.pem"), true)).is_ok());
+        for (t, error) in [
+            (tls(None, None, false), "needs a cert and a key"),
+            (tls(Some("cert.pem"), None, false), "specified together"),
+            (tls(None, Some("key.pem"), true), "specified together"),
+        ] {
+            assert!(parse_tls(&t).unwrap_err().contains(error), "{}", error);
+        }
+        assert_eq!(tls(None, None, true).cert_path(), SELF_SIGNED_CERT);
+    }
+
+    #[test]
+    fn redirect_port() {
+        let mut t = tls(None, None, true);
+        t.redirect_port = Some(0);
+        assert!(parse_tls(&t).is_err());
+
+        let listeners = parse_listeners("0.0.0.0,[::1]:8443,unix:/tmp/a.sock", 443).unwrap();
+        t.redirect_port = Some(80);
+        assert!(parse_redirect_port(&t, &listeners).is_ok());
+        t.redirect_port = Some(443);
+        let error = parse_redirect_port(&t, &listeners).unwrap_err();
+        assert!(error.contains("already used by 0.0.0.0:443"), "{}", error);
+        t.redirect_port = Some(8443);
+        assert!(parse_redirect_port(&t, &listeners).is_err());
+        t.redirect_port = None;
+        assert!(parse_redirect_port(&t, &listeners).is_ok());
+    }
+}
@@ -0,0 +1,682 @@
+use crate::baseline::parse_approve_token;
+use crate::schema::config_schema;
+use crate::settings::{
+    parse_file_format, parse_host, parse_interval, parse_listeners, parse_server_ip,
+    parse_target_browser, parse_url, Layers, Settings,
+};
+use crate::target::target_errors;
+use crate::tls::{parse_redirect_port, parse_tls};
+use serde_json::{json, Value};
+use std::fmt;
+
+pub struct Issue {
+    pub path: String,
+    pub message: String,
+    // configuration file line, environment variable or command line
+    pub location: Option<String>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}: {}", location, self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+// every problem of the effective configuration instead of the first one:
+// unknown keys, missing keys and bad types against the settings schema, then
+// the values rejected by the parse_* validators
+pub fn check_config(layers: &Layers) -> anyhow::Result<Vec<Issue>> {
+    let schema = config_schema()?;
+    let definitions = schema.get("definitions").cloned().unwrap_or(Value::Null);
+    let mut problems = Vec::new();
+    let mut value = layers.value()?;
+    check_value(&mut value, &schema, &definitions, "", &mut problems);
+
+    // values already reported (or replaced by a placeholder) are not validated again
+    if let Ok(mut s) = serde_json::from_value::<Settings>(value) {
+        s.inherit_proxy();
+        let mut invalid = Vec::new();
+        check_settings(&s, &mut invalid);
+        for (path, message) in invalid {
+            let reported = problems.iter().any(|(p, _)| {
+                !p.is_empty() && (path.starts_with(p.as_str()) || p.starts_with(&path))
+            });
+            if !reported {
+                problems.push((path, message));
+            }
+        }
+    }
+
+    Ok(problems
+        .into_iter()
+        .map(|(path, message)| {
+            let top_key = path.split(['.', '[']).next().unwrap_or_default();
+            let location = match (layers.source(top_key), layers.file()) {
+                (Some(source), Some((file, text))) if source == file.display().to_string() => {
+                    Some(match find_line(text, &path) {
+                        Some(line) => format!("{}:{}", source, line),
+                        None => source.to_owned(),
+                    })
+                }
+                (Some(source), _) if source != "default" => Some(source.to_owned()),
+                _ => None,
+            };
+            Issue {
+                path: if path.is_empty() {
+                    "(root)".to_owned()
+                } else {
+                    path
+                },
+                message: message.replace('\n', " "),
+                location,
+            }
+        })
+        .collect())
+}
+
+fn check_settings(s: &Settings, problems: &mut Vec<(String, String)>) {
+    let mut check = |path: String, result: Result<(), String>| {
+        if let Err(e) = result {
+            problems.push((path, e));
+        }
+    };
+
+    check("host".to_owned(), parse_host(&s.host).map(drop));
+    check(
+        "target_browser".to_owned(),
+        parse_target_browser(&s.target_browser).map(drop),
+    );
+    check(
+        "file_format".to_owned(),
+        parse_file_format(&s.file_format).map(drop),
+    );
+    check(
+        "interval".to_owned(),
+        parse_interval(&s.interval.to_string()).map(drop),
+    );
+    if !s.server_ip.is_empty() {
+        check(
+            "server_ip".to_owned(),
+            parse_server_ip(&s.server_ip).map(drop),
+        );
+    }
+    if let Some(t) = &s.tls {
+        check("tls".to_owned(), parse_tls(t));
+        if let Ok(listeners) = parse_listeners(&s.host, s.port) {
+            check(
+                "tls.redirect_port".to_owned(),
+                parse_redirect_port(t, &listeners),
+            );
+        }
+    }
+    if let Some(t) = &s.approve_token {
+        check("approve_token".to_owned(), parse_approve_token(t).map(drop));
+    }
+    if !s.target.url.is_empty() {
+        check("url".to_owned(), parse_url(&s.target.url).map(drop));
+    }
+    for (path, e) in target_errors(&s.target) {
+        check(path, Err(e));
+    }
+
+    let mut names = vec![s.target.name()];
+    for (i, t) in s.targets.iter().enumerate() {
+        check(format!("targets[{}].url", i), parse_url(&t.url).map(drop));
+        for (path, e) in target_errors(t) {
+            check(format!("targets[{}].{}", i, path), Err(e));
+        }
+
+        let name = t.name();
+        if names.contains(&name) {
+            check(
+                format!("targets[{}].name", i),
+                Err(format!("target name '{}' is used more than once", name)),
+            );
+        }
+        names.push(name);
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn resolve<'a>(schema: &'a Value, definitions: &'a Value) -> &'a Value {
+    match schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix("#/definitions/"))
+        .and_then(|name| definitions.get(name))
+    {
+        Some(s) => resolve(s, definitions),
+        None => schema,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        t => type_name(value) == t,
+    }
+}
+
+fn placeholder(schema: &Value, definitions: &Value) -> Value {
+    let schema = resolve(schema, definitions);
+    let alternatives = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .or_else(|| schema.get("allOf"))
+        .and_then(Value::as_array);
+    if let Some(alternatives) = alternatives {
+        return match alternatives
+            .iter()
+            .find(|s| s.get("type") == Some(&json!("null")))
+        {
+            Some(_) => Value::Null,
+            None => alternatives
+                .first()
+                .map_or(Value::Null, |s| placeholder(s, definitions)),
+        };
+    }
+    if let Some(value) = schema.get("enum").and_then(|v| v.get(0)) {
+        return value.clone();
+    }
+
+    let t = match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().find(|t| *t != "null"),
+        t => t,
+    };
+    match t.and_then(Value::as_str) {
+        Some("string") => json!(""),
+        Some("integer") | Some("number") => json!(0),
+        Some("boolean") => json!(false),
+        Some("array") => json!([]),
+        Some("object") => {
+            let mut object = serde_json::Map::new();
+            let properties = schema.get("properties");
+            let required = schema.get("required").and_then(Value::as_array);
+            for key in required.into_iter().flatten().filter_map(Value::as_str) {
+                let s = properties.and_then(|p| p.get(key)).unwrap_or(&Value::Null);
+                object.insert(key.to_owned(), placeholder(s, definitions));
+            }
+            Value::Object(object)
+        }
+        _ => Value::Null,
+    }
+}
+
+// the subset of JSON Schema generated by schemars for the settings types,
+// invalid values are replaced by a placeholder of the expected type so the
+// configuration can still be deserialized for the parse_* validators
+fn check_value(
+    value: &mut Value,
+    schema: &Value,
+    definitions: &Value,
+    path: &str,
+    problems: &mut Vec<(String, String)>,
+) {
+    let schema = resolve(schema, definitions);
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for s in all {
+            check_value(value, s, definitions, path, problems);
+        }
+    }
+
+    let alternatives = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array);
+    if let Some(alternatives) = alternatives {
+        let mut closest: Option<Vec<(String, String)>> = None;
+        for s in alternatives {
+            let mut p = Vec::new();
+            check_value(&mut value.clone(), s, definitions, path, &mut p);
+            if p.is_empty() {
+                check_value(value, s, definitions, path, problems);
+                return;
+            } else if closest.as_ref().is_none_or(|c| p.len() < c.len()) {
+                closest = Some(p);
+            }
+        }
+
+        // externally tagged enums (steps) are objects with a single required key
+        let tags: Vec<&str> = alternatives
+            .iter()
+            .filter_map(|s| resolve(s, definitions).get("required")?.as_array()?.first())
+            .filter_map(Value::as_str)
+            .collect();
+        let tagged = value.as_object().filter(|o| o.len() == 1);
+        match tagged.and_then(|o| o.keys().next()) {
+            Some(key) if tags.len() == alternatives.len() && !tags.contains(&key.as_str()) => {
+                problems.push((
+                    child_path(path, key),
+                    format!("unknown key, expected one of: {}", tags.join(", ")),
+                ));
+            }
+            _ => problems.extend(closest.unwrap_or_default()),
+        }
+        *value = placeholder(schema, definitions);
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(t) => vec![t],
+            Value::Array(a) => a.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            problems.push((
+                path.to_owned(),
+                format!(
+                    "expected {}, found {}",
+                    types.join(" or "),
+                    type_name(value)
+                ),
+            ));
+            *value = placeholder(schema, definitions);
+            return;
+        }
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        // the validators trim and lowercase their input
+        let matches = |v: &Value| match (v.as_str(), value.as_str()) {
+            (Some(v), Some(value)) => v.eq_ignore_ascii_case(value.trim()),
+            _ => v == value,
+        };
+        if !values.iter().any(matches) {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            problems.push((
+                path.to_owned(),
+                format!("expected one of: {}", values.join(", ")),
+            ));
+            *value = placeholder(schema, definitions);
+            return;
+        }
+    }
+
+    if let Some(n) = value.as_f64() {
+        let maximum = schema.get("maximum").and_then(Value::as_f64).or(
+            match schema.get("format").and_then(Value::as_str) {
+                Some("uint8") => Some(u8::MAX as f64),
+                Some("uint16") => Some(u16::MAX as f64),
+                Some("uint32") => Some(u32::MAX as f64),
+                _ => None,
+            },
+        );
+        let minimum = schema.get("minimum").and_then(Value::as_f64);
+        if minimum.is_some_and(|min| n < min) || maximum.is_some_and(|max| n > max) {
+            let bound = |b: Option<f64>| b.map_or("..".to_owned(), |b| b.to_string());
+            problems.push((
+                path.to_owned(),
+                format!(
+                    "{} is out of range ({} to {})",
+                    n,
+                    bound(minimum),
+                    bound(maximum)
+                ),
+            ));
+            *value = placeholder(schema, definitions);
+            return;
+        }
+    }
+
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array_mut()) {
+        for (i, v) in array.iter_mut().enumerate() {
+            check_value(v, items, definitions, &format!("{}[{}]", path, i), problems);
+        }
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties").filter(|s| s.is_object());
+        for (key, v) in object.iter_mut() {
+            match (properties.and_then(|p| p.get(key)), additional) {
+                (Some(s), _) | (None, Some(s)) => {
+                    check_value(v, s, definitions, &child_path(path, key), problems)
+                }
+                (None, None) if properties.is_some() => {
+                    problems.push((child_path(path, key), "unknown key".to_owned()))
+                }
+                (None, None) => {}
+            }
+        }
+
+        let required = schema.get("required").and_then(Value::as_array);
+        for key in required.into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                problems.push((path.to_owned(), format!("missing key '{}'", key)));
+                let s = properties.and_then(|p| p.get(key)).unwrap_or(&Value::Null);
+                object.insert(key.to_owned(), placeholder(s, definitions));
+            }
+        }
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_key_line(line: &str, key: &str) -> bool {
+    let line = line.trim_start().trim_start_matches("- ").trim_start();
+    let header = line
+        .trim_start_matches('[')
+        .split(']')
+        .next()
+        .unwrap_or_default();
+    line.strip_prefix(key)
+        .is_some_and(|r| r.trim_start().starts_with([':', '=']))
+        || line.starts_with(&format!("\"{}\"", key))
+        || line.starts_with('[') && header.rsplit('.').next() == Some(key)
+}
+
+// line number of the deepest key of path found in a YAML, TOML or JSON file,
+// each key is searched after the line of its parent
+fn find_line(text: &str, path: &str) -> Option<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut start = 0;
+    let mut found = None;
+    for segment in path.split('.') {
+        let mut parts = segment.split('[');
+        let key = parts.next().unwrap_or_default();
+        match lines[start..].iter().position(|l| is_key_line(l, key)) {
+            Some(i) => {
+                start += i;
+                found = Some(start);
+            }
+            None => break,
+        }
+
+        for index in parts.filter_map(|p| p.trim_end_matches(']').parse::<usize>().ok()) {
+            let key_line = lines[start].trim();
+            let item = if key_line.starts_with("[[") {
+                // TOML array of tables, one header per item
+                lines
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .filter(|(_, l)| l.trim() == key_line)
+                    .nth(index)
+            } else {
+                // YAML block sequence, one dash per item at the same indentation
+                let indent = lines[start + 1..]
+                    .iter()
+                    .find(|l| !l.trim().is_empty())
+                    .map(|l| indentation(l))
+                    .unwrap_or_default();
+                lines
+                    .iter()
+                    .enumerate()
+                    .skip(start + 1)
+                    .take_while(|(_, l)| l.trim().is_empty() || indentation(l) >= indent)
+                    .filter(|(_, l)| indentation(l) == indent && l.trim_start().starts_with('-'))
+                    .nth(index)
+            };
+            match item {
+                Some((i, _)) => {
+                    start = i;
+                    found = Some(i);
+                }
+                None => return found.map(|i| i + 1),
+            }
+        }
+    }
+
+    found.map(|i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Listener;
+    use crate::steps::Step;
+    use crate::target::Target;
+
+    fn problems(mut value: Value) -> (Value, Vec<(String, String)>) {
+        let schema = config_schema().unwrap();
+        let definitions = schema["definitions"].clone();
+        let mut problems = Vec::new();
+        check_value(&mut value, &schema, &definitions, "", &mut problems);
+        (value, problems)
+    }
+
+    fn has(problems: &[(String, String)], path: &str, message: &str) -> bool {
+        problems
+            .iter()
+            .any(|(p, m)| p == path && m.starts_with(message))
+    }
+
+    #[test]
+    fn valid_config() {
+        let (_, p) = problems(json!({
+            "url": "https://www.gnu.org",
+            "port": 1337,
+            "steps": [{"click": "#accept"}, {"type": {"selector": "#q", "text": "gnu"}}],
+            "targets": [{"name": "pricing", "url": "https://example.com/pricing"}],
+        }));
+        assert!(p.is_empty(), "{:?}", p);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let (_, p) = problems(json!({
+            "colour": "red",
+            "checks": {"fail_on": ["404"]},
+            "targets": [{"url": "https://example.com", "colour": "red"}],
+        }));
+        assert!(has(&p, "colour", "unknown key"));
+        assert!(has(&p, "checks.fail_on", "unknown key"));
+        assert!(has(&p, "targets[0].colour", "unknown key"));
+    }
+
+    #[test]
+    fn unknown_step_tag() {
+        let (value, p) = problems(json!({"steps": [{"click": "#a"}, {"clik": "#b"}]}));
+        assert_eq!(p.len(), 1, "{:?}", p);
+        assert!(has(
+            &p,
+            "steps[1].clik",
+            "unknown key, expected one of: navigate, click"
+        ));
+        // the step is replaced so the settings can still be deserialized
+        assert!(serde_json::from_value::<Step>(value["steps"][1].clone()).is_ok());
+    }
+
+    #[test]
+    fn invalid_values_are_replaced() {
+        let (value, p) = problems(json!({
+            "port": "http",
+            "interval": 0,
+            "file_format": "gif",
+            "target_browser": " Chrome",
+        }));
+        assert!(has(&p, "port", "expected integer, found string"));
+        assert!(has(&p, "interval", "0 is out of range (1 to 1440)"));
+        assert!(has(&p, "file_format", "expected one of: \"png\""));
+        assert!(!p.iter().any(|(path, _)| path == "target_browser"));
+        assert_eq!(value["port"], json!(0));
+        assert_eq!(value["file_format"], json!("png"));
+    }
+
+    #[test]
+    fn resolve_follows_references() {
+        let definitions = json!({
+            "A": {"$ref": "#/definitions/B"},
+            "B": {"type": "string"},
+        });
+        let schema = json!({"$ref": "#/definitions/A"});
+        assert_eq!(resolve(&schema, &definitions), &json!({"type": "string"}));
+        let unknown = json!({"$ref": "#/definitions/C"});
+        assert_eq!(resolve(&unknown, &definitions), &unknown);
+    }
+
+    #[test]
+    fn placeholders() {
+        let definitions = json!({
+            "Pair": {
+                "type": "object",
+                "properties": {"a": {"type": "string"}, "b": {"type": "integer"}},
+                "required": ["a"],
+            },
+        });
+        let nullable = json!({"anyOf": [{"$ref": "#/definitions/Pair"}, {"type": "null"}]});
+        assert_eq!(placeholder(&nullable, &definitions), Value::Null);
+        let pair = json!({"$ref": "#/definitions/Pair"});
+        assert_eq!(placeholder(&pair, &definitions), json!({"a": ""}));
+        let browser = json!({"type": "string", "enum": ["chrome", "firefox"]});
+        assert_eq!(placeholder(&browser, &definitions), json!("chrome"));
+    }
+
+    #[test]
+    fn listeners() {
+        let tcp = |a: &str| Listener::Tcp(a.parse().unwrap());
+        for (host, expected) in [
+            ("127.0.0.1", vec![tcp("127.0.0.1:8080")]),
+            ("0.0.0.0:80", vec![tcp("0.0.0.0:80")]),
+            ("::", vec![tcp("[::]:8080")]),
+            ("::1", vec![tcp("[::1]:8080")]),
+            ("[::1]", vec![tcp("[::1]:8080")]),
+            ("[::1]:9000", vec![tcp("[::1]:9000")]),
+            (
+                " 192.168.1.70 , ::1,unix:/run/observer.sock",
+                vec![
+                    tcp("192.168.1.70:8080"),
+                    tcp("[::1]:8080"),
+                    Listener::Unix("/run/observer.sock".into()),
+                ],
+            ),
+            (
+                "unix:observer.sock",
+                vec![Listener::Unix("observer.sock".into())],
+            ),
+            // the same address on another port
+            (
+                "127.0.0.1,127.0.0.1:9000",
+                vec![tcp("127.0.0.1:8080"), tcp("127.0.0.1:9000")],
+            ),
+        ] {
+            assert!(parse_listeners(host, 8080) == Ok(expected), "{}", host);
+        }
+
+        for (host, error) in [
+            ("", "comma separated list"),
+            ("127.0.0.1,", "comma separated list"),
+            ("localhost", "comma separated list"),
+            ("::1:8080:", "comma separated list"),
+            ("[::1", "comma separated list"),
+            ("256.0.0.1", "comma separated list"),
+            ("127.0.0.1:70000", "comma separated list"),
+            ("unix:", "comma separated list"),
+            (
+                "127.0.0.1,127.0.0.1:8080",
+                "host lists 127.0.0.1:8080 more than once",
+            ),
+            (
+                "unix:a.sock,unix:a.sock",
+                "host lists unix:a.sock more than once",
+            ),
+        ] {
+            match parse_listeners(host, 8080) {
+                Err(e) => assert!(e.contains(error), "{}: {}", host, e),
+                Ok(_) => panic!("{} should be rejected", host),
+            }
+        }
+        assert_eq!(
+            parse_host(" 127.0.0.1 , unix:/tmp/a.sock").unwrap(),
+            "127.0.0.1,unix:/tmp/a.sock"
+        );
+    }
+
+    #[test]
+    fn leaf_paths() {
+        let target: Target = serde_json::from_value(json!({
+            "url": "https://example.com",
+            "checks": {"fail_on_status": ["404", "5xx"]},
+            "steps": [{"click": "#a"}, {"wait": -1}],
+            "crawl": {"exclude": ["(", "/admin"]},
+        }))
+        .unwrap();
+        let paths: Vec<_> = target_errors(&target).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(
+            paths,
+            ["checks.fail_on_status[1]", "steps[1]", "crawl.exclude[0]"]
+        );
+
+        let text = "targets:\n\
+            \x20 - url: https://example.com\n\
+            \x20   checks:\n\
+            \x20     fail_on_status:\n\
+            \x20       - \"404\"\n\
+            \x20       - \"5xx\"\n";
+        assert_eq!(
+            find_line(text, "targets[0].checks.fail_on_status[1]"),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn yaml_lines() {
+        let text = "url: https://www.gnu.org\n\
+            checks:\n\
+            \x20 tls_expiry_warning_days: 30\n\
+            steps:\n\
+            \x20 - click: \"#a\"\n\
+            \x20 - wait: 1\n\
+            targets:\n\
+            \x20 - name: first\n\
+            \x20   url: https://example.com\n\
+            \x20 - name: second\n\
+            \x20   url: example.com\n";
+        assert_eq!(find_line(text, "url"), Some(1));
+        assert_eq!(find_line(text, "checks.tls_expiry_warning_days"), Some(3));
+        assert_eq!(find_line(text, "steps[1]"), Some(6));
+        assert_eq!(find_line(text, "targets[1].url"), Some(11));
+        assert_eq!(find_line(text, "targets[1].name"), Some(10));
+        // the closest line found when the key is not in the file
+        assert_eq!(find_line(text, "targets[1].port"), Some(10));
+        assert_eq!(find_line(text, "colour"), None);
+    }
+
+    #[test]
+    fn toml_lines() {
+        let text = "url = \"https://www.gnu.org\"\n\
+            port = 70000\n\
+            \n\
+            [checks]\n\
+            tls_expiry_warning_days = 30\n\
+            \n\
+            [[targets]]\n\
+            name = \"first\"\n\
+            url = \"https://example.com\"\n\
+            \n\
+            [[targets]]\n\
+            name = \"second\"\n\
+            url = \"example.com\"\n";
+        assert_eq!(find_line(text, "port"), Some(2));
+        assert_eq!(find_line(text, "checks.tls_expiry_warning_days"), Some(5));
+        assert_eq!(find_line(text, "targets[0].url"), Some(9));
+        assert_eq!(find_line(text, "targets[1].url"), Some(13));
+    }
+}